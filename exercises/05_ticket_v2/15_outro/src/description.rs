@@ -2,6 +2,13 @@
 //   enforcing that the description is not empty and is not longer than 500 characters.
 //   Implement the traits required to make the tests pass too.
 
+use ticket_fields::ValidationPolicy;
+use unicode_segmentation::UnicodeSegmentation;
+
+// Shared with the other ticket types, see `ticket_fields::ValidationPolicy`.
+// Like there, it counts graphemes rather than bytes.
+const MAX_LENGTH: usize = ValidationPolicy::DEFAULT.description.max_length;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct TicketDescription(String);
 
@@ -12,8 +19,10 @@ impl TryFrom<String> for TicketDescription {
         if value.is_empty() {
             return Err(TicketDescriptionParseError::DescriptionEmpty);
         }
-        if value.len() > 500 {
-            return Err(TicketDescriptionParseError::LongerThanMaxCharacters(500));
+        if value.graphemes(true).count() > MAX_LENGTH {
+            return Err(TicketDescriptionParseError::LongerThanMaxCharacters(
+                MAX_LENGTH,
+            ));
         }
 
         Ok(Self(value))
@@ -27,8 +36,10 @@ impl TryFrom<&str> for TicketDescription {
         if value.is_empty() {
             return Err(TicketDescriptionParseError::DescriptionEmpty);
         }
-        if value.len() > 500 {
-            return Err(TicketDescriptionParseError::LongerThanMaxCharacters(500));
+        if value.graphemes(true).count() > MAX_LENGTH {
+            return Err(TicketDescriptionParseError::LongerThanMaxCharacters(
+                MAX_LENGTH,
+            ));
         }
 
         Ok(Self(value.to_string()))
@@ -40,7 +51,7 @@ pub enum TicketDescriptionParseError {
     #[error("The description cannot be empty")]
     DescriptionEmpty,
     #[error("The description cannot be longer than {0} characters")]
-    LongerThanMaxCharacters(u32),
+    LongerThanMaxCharacters(usize),
 }
 
 #[cfg(test)]
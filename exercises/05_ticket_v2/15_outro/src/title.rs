@@ -2,6 +2,13 @@
 //   enforcing that the title is not empty and is not longer than 50 characters.
 //   Implement the traits required to make the tests pass too.
 
+use ticket_fields::ValidationPolicy;
+use unicode_segmentation::UnicodeSegmentation;
+
+// Shared with the other ticket types, see `ticket_fields::ValidationPolicy`.
+// Like there, it counts graphemes rather than bytes.
+const MAX_LENGTH: usize = ValidationPolicy::DEFAULT.title.max_length;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct TicketTitle(String);
 
@@ -12,8 +19,8 @@ impl TryFrom<String> for TicketTitle {
         if value.is_empty() {
             return Err(TicketTitleParseError::DescriptionEmpty);
         }
-        if value.len() > 50 {
-            return Err(TicketTitleParseError::LongerThanMaxCharacters(50));
+        if value.graphemes(true).count() > MAX_LENGTH {
+            return Err(TicketTitleParseError::LongerThanMaxCharacters(MAX_LENGTH));
         }
 
         Ok(Self(value))
@@ -27,8 +34,8 @@ impl TryFrom<&str> for TicketTitle {
         if value.is_empty() {
             return Err(TicketTitleParseError::DescriptionEmpty);
         }
-        if value.len() > 50 {
-            return Err(TicketTitleParseError::LongerThanMaxCharacters(50));
+        if value.graphemes(true).count() > MAX_LENGTH {
+            return Err(TicketTitleParseError::LongerThanMaxCharacters(MAX_LENGTH));
         }
 
         Ok(Self(value.to_string()))
@@ -40,7 +47,7 @@ pub enum TicketTitleParseError {
     #[error("The title cannot be empty")]
     DescriptionEmpty,
     #[error("The title cannot be longer than {0} characters")]
-    LongerThanMaxCharacters(u32),
+    LongerThanMaxCharacters(usize),
 }
 
 #[cfg(test)]
@@ -72,6 +79,14 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_length_is_counted_in_graphemes() {
+        let input = "日本語で書かれたチケットのタイトルです。二十文字";
+        assert!(input.len() > 50);
+        let title = TicketTitle::try_from(input).unwrap();
+        assert_eq!(title.0, input);
+    }
+
     #[test]
     fn test_try_from_str() {
         let title = TicketTitle::try_from("A title").unwrap();
@@ -0,0 +1,141 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::data::{Ticket, TicketAssignee};
+use crate::error::{AppError, AppResult};
+
+// Declared from least to most privileged, so that `Ord` follows privileges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    // Can read tickets.
+    Reader,
+    // Can also create tickets, and change the ones they created or are assigned to.
+    Editor,
+    // Can also change and delete any ticket.
+    Admin,
+}
+
+// The authenticated user behind a request.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Caller {
+    pub user: TicketAssignee,
+    pub role: Role,
+}
+
+impl Caller {
+    pub fn require(&self, role: Role) -> AppResult<()> {
+        if self.role < role {
+            return Err(AppError::Forbidden);
+        }
+        Ok(())
+    }
+
+    pub fn may_change(&self, ticket: &Ticket) -> bool {
+        match self.role {
+            Role::Reader => false,
+            Role::Editor => {
+                ticket.created_by.as_ref() == Some(&self.user)
+                    || ticket.assignee.as_ref() == Some(&self.user)
+            }
+            Role::Admin => true,
+        }
+    }
+}
+
+// The `auth` section of the server config: the bearer tokens that are accepted,
+// and who they belong to. Without any token, authentication is disabled.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    pub tokens: BTreeMap<String, Caller>,
+}
+
+impl AuthConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.tokens.is_empty()
+    }
+
+    // Resolves the value of an `Authorization: Bearer <token>` header.
+    //
+    // Tokens are compared by their hashes rather than looked up directly, so that
+    // the time it takes does not tell how much of a guess matches a real token.
+    pub fn authenticate(&self, authorization: Option<&str>) -> AppResult<&Caller> {
+        let token = authorization
+            .and_then(|authorization| authorization.strip_prefix("Bearer "))
+            .ok_or(AppError::Unauthorized)?;
+        let digest = Sha256::digest(token.trim());
+
+        self.tokens
+            .iter()
+            .find(|(known, _)| Sha256::digest(known) == digest)
+            .map(|(_, caller)| caller)
+            .ok_or(AppError::Unauthorized)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TicketDraft;
+    use crate::store::TicketId;
+
+    fn caller(user: &str, role: Role) -> Caller {
+        Caller {
+            user: user.try_into().unwrap(),
+            role,
+        }
+    }
+
+    #[test]
+    fn test_authenticate() {
+        let config: AuthConfig = serde_json::from_str(
+            r#"{ "tokens": { "secret": { "user": "alice", "role": "editor" } } }"#,
+        )
+        .unwrap();
+        assert!(config.is_enabled());
+
+        assert_eq!(
+            config.authenticate(Some("Bearer secret")).unwrap(),
+            &caller("alice", Role::Editor)
+        );
+        for authorization in [
+            None,
+            Some("Bearer nope"),
+            Some("secret"),
+            Some("Basic secret"),
+        ] {
+            assert!(matches!(
+                config.authenticate(authorization),
+                Err(AppError::Unauthorized)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_who_may_change_a_ticket() {
+        let draft = TicketDraft {
+            assignee: Some("bob".try_into().unwrap()),
+            created_by: Some("alice".try_into().unwrap()),
+            ..TicketDraft::new(
+                "A title".try_into().unwrap(),
+                "A description".try_into().unwrap(),
+            )
+        };
+        let ticket = Ticket::from_draft(TicketId(0), draft);
+
+        assert!(caller("alice", Role::Editor).may_change(&ticket));
+        assert!(caller("bob", Role::Editor).may_change(&ticket));
+        assert!(!caller("carol", Role::Editor).may_change(&ticket));
+        assert!(!caller("alice", Role::Reader).may_change(&ticket));
+        assert!(caller("carol", Role::Admin).may_change(&ticket));
+
+        assert!(caller("carol", Role::Editor).require(Role::Editor).is_ok());
+        assert!(matches!(
+            caller("carol", Role::Editor).require(Role::Admin),
+            Err(AppError::Forbidden)
+        ));
+    }
+}
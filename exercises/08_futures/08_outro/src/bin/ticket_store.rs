@@ -0,0 +1,59 @@
+// Dumps a store directory to stdout, or restores one from stdin.
+//
+//     ticket_store export <dir> [--format jsonl|csv] > tickets.jsonl
+//     ticket_store import <dir> [--format jsonl|csv] < tickets.jsonl
+use std::io::{self, BufReader};
+use std::process::ExitCode;
+
+use outro_08::storage::LogStorage;
+use outro_08::store::TicketStore;
+use outro_08::transfer::TransferFormat;
+
+const USAGE: &str = "usage: ticket_store <export|import> <dir> [--format jsonl|csv]";
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (command, dir, format) = match parse_args(&args) {
+        Some(args) => args,
+        None => {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(command, dir, format).await {
+        Ok(count) => {
+            eprintln!("{command}ed {count} tickets");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Option<(&str, &str, TransferFormat)> {
+    let (command, dir, format) = match args {
+        [command, dir] => (command, dir, TransferFormat::JsonLines),
+        [command, dir, flag, format] if flag == "--format" => (
+            command,
+            dir,
+            TransferFormat::try_from(format.as_str()).ok()?,
+        ),
+        _ => return None,
+    };
+    matches!(command.as_str(), "export" | "import").then_some((command, dir, format))
+}
+
+async fn run(command: &str, dir: &str, format: TransferFormat) -> io::Result<usize> {
+    let mut store = TicketStore::open(LogStorage::open(dir)?)?;
+    if command == "export" {
+        store.export(format, io::stdout().lock()).await
+    } else {
+        let count = store.import(format, BufReader::new(io::stdin().lock()))?;
+        store.flush()?;
+        Ok(count)
+    }
+}
@@ -0,0 +1,67 @@
+use salvo::oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::data::{Ticket, TicketPatch};
+use crate::error::{AppError, AppErrorWriter, AppResult};
+use crate::store::TicketId;
+
+// One item of `PATCH /api/ticket/bulk`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+#[salvo(schema(symbol = "BulkPatch"))]
+pub struct BulkPatch {
+    pub id: TicketId,
+    pub patch: TicketPatch,
+    // Like `If-Match`: the patch is only applied to this version of the ticket.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+}
+
+// The outcome of one item of a bulk request, in the order of the request.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+#[salvo(schema(symbol = "BulkItem"))]
+pub struct BulkItem {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<TicketId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<AppErrorWriter>,
+}
+
+impl From<AppResult<TicketId>> for BulkItem {
+    fn from(result: AppResult<TicketId>) -> Self {
+        match result {
+            Ok(id) => Self {
+                id: Some(id),
+                version: None,
+                error: None,
+            },
+            Err(e) => Self::failed(None, &e),
+        }
+    }
+}
+
+impl BulkItem {
+    pub fn patched(id: TicketId, result: AppResult<Ticket>) -> Self {
+        match result {
+            Ok(ticket) => Self {
+                id: Some(ticket.id),
+                version: Some(ticket.version),
+                error: None,
+            },
+            Err(e) => Self::failed(Some(id), &e),
+        }
+    }
+
+    fn failed(id: Option<TicketId>, error: &AppError) -> Self {
+        Self {
+            id,
+            version: None,
+            error: Some(error.into()),
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use reqwest::header::IF_MATCH;
+use reqwest::{Method, RequestBuilder, Response, Url};
+use serde::Deserialize;
+
+use crate::data::{Ticket, TicketDraft, TicketPatch};
+use crate::error::{AppErrorWriter, ClientError, ClientResult};
+use crate::server::CreatedTicket;
+use crate::store::TicketId;
+
+#[derive(Clone, Debug)]
+pub struct ClientConfig {
+    // The root of the server, e.g. `http://localhost:8080/`.
+    pub base_url: Url,
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    // Sent as `Authorization: Bearer <token>`, for servers with authentication enabled.
+    pub token: Option<String>,
+}
+
+impl ClientConfig {
+    pub fn new(mut base_url: Url) -> Self {
+        // Without a trailing slash, `Url::join` would replace the last path segment.
+        if !base_url.path().ends_with('/') {
+            base_url.set_path(&format!("{}/", base_url.path()));
+        }
+
+        Self {
+            base_url,
+            timeout: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(5),
+            token: None,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+}
+
+// A typed client for the `/api/ticket` endpoints.
+#[derive(Clone, Debug)]
+pub struct TicketClient {
+    http: reqwest::Client,
+    tickets_url: Url,
+    token: Option<String>,
+}
+
+impl TicketClient {
+    pub fn new(config: ClientConfig) -> ClientResult<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .connect_timeout(config.connect_timeout)
+            .build()?;
+        let tickets_url = config
+            .base_url
+            .join("api/ticket/")
+            .map_err(|_| ClientError::InvalidBaseUrl(config.base_url))?;
+
+        Ok(Self {
+            http,
+            tickets_url,
+            token: config.token,
+        })
+    }
+
+    pub async fn create(&self, draft: &TicketDraft) -> ClientResult<TicketId> {
+        let res = self
+            .request(Method::POST, self.tickets_url.clone())
+            .json(draft)
+            .send()
+            .await?;
+        let created: CreatedTicket = decode(res).await?;
+        Ok(created.id)
+    }
+
+    pub async fn get(&self, id: TicketId) -> ClientResult<Ticket> {
+        let res = self
+            .request(Method::GET, self.ticket_url(id)?)
+            .send()
+            .await?;
+        decode(res).await
+    }
+
+    // With `expected_version`, the patch is only applied if the ticket is still at that version.
+    pub async fn patch(
+        &self,
+        id: TicketId,
+        patch: &TicketPatch,
+        expected_version: Option<u64>,
+    ) -> ClientResult<Ticket> {
+        let mut req = self
+            .request(Method::PATCH, self.ticket_url(id)?)
+            .json(patch);
+        if let Some(version) = expected_version {
+            req = req.header(IF_MATCH, format!("\"{version}\""));
+        }
+        decode(req.send().await?).await
+    }
+
+    fn request(&self, method: Method, url: Url) -> RequestBuilder {
+        let req = self.http.request(method, url);
+        match &self.token {
+            Some(token) => req.bearer_auth(token),
+            None => req,
+        }
+    }
+
+    fn ticket_url(&self, id: TicketId) -> ClientResult<Url> {
+        self.tickets_url
+            .join(&id.0.to_string())
+            .map_err(|_| ClientError::InvalidBaseUrl(self.tickets_url.clone()))
+    }
+}
+
+// Error responses carry an `AppErrorWriter` body, anything else is a bug on one side.
+async fn decode<T: for<'de> Deserialize<'de>>(res: Response) -> ClientResult<T> {
+    let status = res.status();
+    if status.is_success() {
+        return Ok(res.json().await?);
+    }
+
+    match res.json::<AppErrorWriter>().await {
+        Ok(error) => Err(ClientError::Api { status, error }),
+        Err(_) => Err(ClientError::UnexpectedResponse(status)),
+    }
+}
@@ -0,0 +1,72 @@
+use chrono::{DateTime, Utc};
+use salvo::oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+use ticket_fields::ValidationPolicy;
+
+use crate::data::{TicketAssignee, TicketQuery};
+use crate::error::AppError;
+use crate::store::TicketId;
+
+pub use ticket_fields::CommentBody;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, ToSchema)]
+#[salvo(schema(symbol = "CommentId"))]
+pub struct CommentId(pub u64);
+
+// Authors are people, so they are validated like assignees.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[salvo(schema(symbol = "Comment"))]
+pub struct Comment {
+    pub id: CommentId,
+    pub ticket_id: TicketId,
+    #[salvo(schema(value_type = String))]
+    pub author: TicketAssignee,
+    #[salvo(schema(value_type = String))]
+    pub body: CommentBody,
+    #[salvo(schema(value_type = String, format = DateTime))]
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[salvo(schema(symbol = "CommentDraft"))]
+pub struct CommentDraft {
+    #[salvo(schema(value_type = String))]
+    pub author: TicketAssignee,
+    #[salvo(schema(value_type = String))]
+    pub body: CommentBody,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CommentQuery {
+    // The first id the page may start from, as returned in `CommentPage::next_cursor`.
+    pub cursor: Option<CommentId>,
+    pub limit: Option<usize>,
+}
+
+impl CommentQuery {
+    // Pages are as large as ticket pages.
+    pub fn limit(&self) -> usize {
+        self.limit
+            .unwrap_or(TicketQuery::DEFAULT_LIMIT)
+            .clamp(1, TicketQuery::MAX_LIMIT)
+    }
+}
+
+// Oldest comment first.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+#[salvo(schema(symbol = "CommentPage"))]
+pub struct CommentPage {
+    pub comments: Vec<Comment>,
+    pub next_cursor: Option<CommentId>,
+}
+
+// Returns the draft normalized according to `policy`.
+pub fn validate_comment_draft(
+    comment_draft: CommentDraft,
+    policy: &ValidationPolicy,
+) -> Result<CommentDraft, AppError> {
+    Ok(CommentDraft {
+        author: TicketAssignee::new(comment_draft.author.as_str(), policy)?,
+        body: CommentBody::new(comment_draft.body.as_str(), policy)?,
+    })
+}
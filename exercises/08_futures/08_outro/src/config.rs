@@ -0,0 +1,151 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use ticket_fields::ValidationPolicy;
+
+use crate::auth::AuthConfig;
+use crate::limit::LimitsConfig;
+use crate::storage::Storage;
+use crate::store::TicketStore;
+use crate::workflow::StatusWorkflow;
+
+// Server settings, read from a JSON file.
+// Every section is optional and falls back to its default, except that
+// the store sections that are left out keep whatever the store already has.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation: Option<ValidationPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workflow: Option<StatusWorkflow>,
+    pub auth: AuthConfig,
+    pub limits: LimitsConfig,
+}
+
+impl ServerConfig {
+    // The validation policy can only tighten the default one,
+    // since request bodies and stored tickets are parsed against the default.
+    // A rate limit has to let some requests through.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let config: Self = serde_json::from_str(&contents)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        let is_looser = |policy: &ValidationPolicy| !policy.is_within(&ValidationPolicy::DEFAULT);
+        if config.validation.as_ref().is_some_and(is_looser) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the validation policy must not be looser than the default one",
+            ));
+        }
+        if config.limits.rate.is_some_and(|rate| !rate.is_valid()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the rate limit needs a positive burst and rate",
+            ));
+        }
+
+        Ok(config)
+    }
+
+    pub fn open_store(&self, storage: impl Storage + 'static) -> io::Result<TicketStore> {
+        let mut store = TicketStore::open(storage)?;
+        self.configure(&mut store)?;
+        Ok(store)
+    }
+
+    // Applies the store sections that are set, i.e. the validation policy and the workflow.
+    pub fn configure(&self, store: &mut TicketStore) -> io::Result<()> {
+        if let Some(policy) = self.validation {
+            store.set_policy(policy)?;
+        }
+        if let Some(workflow) = &self.workflow {
+            store.set_workflow(workflow.clone());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TicketDraft;
+    use crate::error::{AppError, TicketDescriptionError};
+    use crate::storage::MemoryStorage;
+    use ticket_fields::Normalization;
+
+    #[test]
+    fn test_load_partial_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        fs::write(
+            &path,
+            r#"{ "validation": { "description": { "min_length": 1, "max_length": 10, "allowed": "multi_line", "normalization": "trim" } } }"#,
+        )
+        .unwrap();
+
+        let config = ServerConfig::load(&path).unwrap();
+        let validation = config.validation.unwrap();
+        assert_eq!(validation.description.max_length, 10);
+        assert_eq!(validation.title, ValidationPolicy::DEFAULT.title);
+        assert_eq!(config.workflow, None);
+
+        let mut store = config.open_store(MemoryStorage).unwrap();
+        let err = store
+            .add_ticket(TicketDraft::new(
+                "Title".try_into().unwrap(),
+                "Longer than ten".try_into().unwrap(),
+            ))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            AppError::TicketDescriptionError(TicketDescriptionError::TooLong {
+                max: 10,
+                actual: 15
+            })
+        ));
+    }
+
+    #[test]
+    fn test_load_invalid_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        fs::write(&path, "not json").unwrap();
+
+        let err = ServerConfig::load(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut policy = ValidationPolicy::DEFAULT;
+        policy.title.max_length = 100;
+        let config = ServerConfig {
+            validation: Some(policy),
+            ..Default::default()
+        };
+        fs::write(&path, serde_json::to_string(&config).unwrap()).unwrap();
+
+        let err = ServerConfig::load(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut policy = ValidationPolicy::DEFAULT;
+        policy.title.normalization = Normalization::None;
+        let config = ServerConfig {
+            validation: Some(policy),
+            ..Default::default()
+        };
+        fs::write(&path, serde_json::to_string(&config).unwrap()).unwrap();
+
+        let err = ServerConfig::load(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        fs::write(
+            &path,
+            r#"{ "limits": { "rate": { "burst": 0, "per_second": 1.0 } } }"#,
+        )
+        .unwrap();
+        let err = ServerConfig::load(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
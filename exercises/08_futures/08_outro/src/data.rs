@@ -1,30 +1,192 @@
 use crate::{
-    error::{
-        AppError, TicketDescriptionError, TicketPatchError, TicketStatusError, TicketTitleError,
-    },
+    error::{AppError, TicketPatchError, TicketStatusError},
+    history::TicketChange,
     store::TicketId,
+    workflow::StatusWorkflow,
 };
 
-use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
 
-const TICKET_TITLE_MAX_LENGTH: usize = 50;
-const TICKET_DESCRIPTION_MAX_LENGTH: usize = 300;
+use chrono::NaiveDate;
 
-#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+use salvo::oapi::ToSchema;
+use serde::{Deserialize, Deserializer, Serialize};
+use ticket_fields::ValidationPolicy;
+
+// The field types validate themselves against the default policy when they are
+// deserialized; the `validate_*` functions below apply the configured policy on top.
+pub use ticket_fields::{TicketAssignee, TicketDescription, TicketTag, TicketTitle};
+
+// Fields whose types come from other crates are described by their JSON representation.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+#[salvo(schema(symbol = "Ticket"))]
 pub struct Ticket {
     pub id: TicketId,
+    #[salvo(schema(value_type = String))]
     pub title: TicketTitle,
+    #[salvo(schema(value_type = String))]
     pub description: TicketDescription,
     pub status: Status,
+    #[serde(default)]
+    #[salvo(schema(value_type = Option<String>))]
+    pub assignee: Option<TicketAssignee>,
+    #[serde(default)]
+    #[salvo(schema(value_type = Vec<String>))]
+    pub tags: BTreeSet<TicketTag>,
+    #[serde(default)]
+    pub priority: Option<Priority>,
+    #[serde(default)]
+    #[salvo(schema(value_type = Option<String>, format = Date))]
+    pub due_date: Option<NaiveDate>,
+    // The user who created the ticket, when authentication is enabled.
+    #[serde(default)]
+    #[salvo(schema(value_type = Option<String>))]
+    pub created_by: Option<TicketAssignee>,
+    // Bumped on every patch that changes something, so that writers can detect concurrent changes.
+    #[serde(default = "Ticket::initial_version")]
+    pub version: u64,
+}
+
+impl Ticket {
+    pub const fn initial_version() -> u64 {
+        1
+    }
+
+    // A new ticket starts out to do, at the initial version.
+    pub fn from_draft(id: TicketId, draft: TicketDraft) -> Self {
+        Self {
+            id,
+            title: draft.title,
+            description: draft.description,
+            status: Status::ToDo,
+            assignee: draft.assignee,
+            tags: draft.tags,
+            priority: draft.priority,
+            due_date: draft.due_date,
+            created_by: draft.created_by,
+            version: Self::initial_version(),
+        }
+    }
+
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        self.status != Status::Done && self.due_date.is_some_and(|due_date| due_date < today)
+    }
+
+    // Returns the changes that the patch actually made:
+    // setting a field to its current value is not a change.
+    pub fn apply(&mut self, patch: TicketPatch) -> Vec<TicketChange> {
+        let mut changes = Vec::new();
+
+        if let Some(title) = patch.title {
+            if title != self.title {
+                changes.push(TicketChange::TitleChanged {
+                    from: std::mem::replace(&mut self.title, title.clone()),
+                    to: title,
+                });
+            }
+        }
+        if let Some(description) = patch.description {
+            if description != self.description {
+                changes.push(TicketChange::DescriptionChanged {
+                    from: std::mem::replace(&mut self.description, description.clone()),
+                    to: description,
+                });
+            }
+        }
+        if let Some(status) = patch.status {
+            if status != self.status {
+                changes.push(TicketChange::StatusChanged {
+                    from: std::mem::replace(&mut self.status, status),
+                    to: status,
+                });
+            }
+        }
+        if let Some(assignee) = patch.assignee {
+            if assignee != self.assignee {
+                changes.push(TicketChange::AssigneeChanged {
+                    from: std::mem::replace(&mut self.assignee, assignee.clone()),
+                    to: assignee,
+                });
+            }
+        }
+        if let Some(priority) = patch.priority {
+            if priority != self.priority {
+                changes.push(TicketChange::PriorityChanged {
+                    from: std::mem::replace(&mut self.priority, priority),
+                    to: priority,
+                });
+            }
+        }
+        if let Some(due_date) = patch.due_date {
+            if due_date != self.due_date {
+                changes.push(TicketChange::DueDateChanged {
+                    from: std::mem::replace(&mut self.due_date, due_date),
+                    to: due_date,
+                });
+            }
+        }
+        let added: BTreeSet<_> = patch
+            .add_tags
+            .into_iter()
+            .filter(|tag| self.tags.insert(tag.clone()))
+            .collect();
+        let removed: BTreeSet<_> = patch
+            .remove_tags
+            .into_iter()
+            .filter(|tag| self.tags.remove(tag))
+            .collect();
+        if !added.is_empty() || !removed.is_empty() {
+            changes.push(TicketChange::TagsChanged { added, removed });
+        }
+        if !changes.is_empty() {
+            self.version += 1;
+        }
+
+        changes
+    }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[salvo(schema(symbol = "TicketDraft"))]
 pub struct TicketDraft {
+    #[salvo(schema(value_type = String))]
     pub title: TicketTitle,
+    #[salvo(schema(value_type = String))]
     pub description: TicketDescription,
+    #[serde(default)]
+    #[salvo(schema(value_type = Option<String>))]
+    pub assignee: Option<TicketAssignee>,
+    #[serde(default)]
+    #[salvo(schema(value_type = Vec<String>))]
+    pub tags: BTreeSet<TicketTag>,
+    #[serde(default)]
+    pub priority: Option<Priority>,
+    #[serde(default)]
+    #[salvo(schema(value_type = Option<String>, format = Date))]
+    pub due_date: Option<NaiveDate>,
+    // Set by the server from the caller, never read from the request body.
+    #[serde(skip)]
+    pub created_by: Option<TicketAssignee>,
 }
 
-#[derive(Clone, Debug, Copy, PartialEq, Eq, Deserialize, Serialize)]
+impl TicketDraft {
+    // The optional fields are left unset.
+    pub fn new(title: TicketTitle, description: TicketDescription) -> Self {
+        Self {
+            title,
+            description,
+            assignee: None,
+            tags: BTreeSet::new(),
+            priority: None,
+            due_date: None,
+            created_by: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, ToSchema)]
+#[salvo(schema(symbol = "Status"))]
 pub enum Status {
     ToDo,
     InProgress,
@@ -52,160 +214,343 @@ impl TryFrom<String> for Status {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
-pub struct TicketTitle(String);
-
-impl TicketTitle {
-    pub fn new(title: String) -> Result<Self, TicketTitleError> {
-        if title.is_empty() {
-            return Err(TicketTitleError::Empty);
-        } else if title.len() > TICKET_TITLE_MAX_LENGTH {
-            return Err(TicketTitleError::TooLong(TICKET_TITLE_MAX_LENGTH));
-        }
-        Ok(Self(title))
-    }
+// Declared from least to most urgent, so that `Ord` follows urgency.
+#[derive(Clone, Debug, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, ToSchema)]
+#[salvo(schema(symbol = "Priority"))]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+    Critical,
 }
 
-impl TryFrom<String> for TicketTitle {
-    type Error = TicketTitleError;
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+#[salvo(schema(symbol = "TicketPatch"))]
+pub struct TicketPatch {
+    #[salvo(schema(value_type = Option<String>))]
+    pub title: Option<TicketTitle>,
+    #[salvo(schema(value_type = Option<String>))]
+    pub description: Option<TicketDescription>,
+    pub status: Option<Status>,
+    // `None` leaves the assignee untouched, `Some(None)` (`null` in JSON) unassigns the ticket.
+    #[serde(
+        default,
+        deserialize_with = "double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[salvo(schema(value_type = Option<String>))]
+    pub assignee: Option<Option<TicketAssignee>>,
+    // Like the assignee, `null` clears the priority or the due date.
+    #[serde(
+        default,
+        deserialize_with = "double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[salvo(schema(value_type = Option<Priority>))]
+    pub priority: Option<Option<Priority>>,
+    #[serde(
+        default,
+        deserialize_with = "double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[salvo(schema(value_type = Option<String>, format = Date))]
+    pub due_date: Option<Option<NaiveDate>>,
+    // Tags are added and removed one by one, so that concurrent patches
+    // touching different tags don't overwrite each other.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    #[salvo(schema(value_type = Vec<String>))]
+    pub add_tags: BTreeSet<TicketTag>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    #[salvo(schema(value_type = Vec<String>))]
+    pub remove_tags: BTreeSet<TicketTag>,
+}
 
-    fn try_from(value: String) -> Result<Self, Self::Error> {
-        Self::new(value)
-    }
+fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
 }
 
-impl TryFrom<&str> for TicketTitle {
-    type Error = TicketTitleError;
+impl TicketPatch {
+    pub fn new(
+        title: Option<TicketTitle>,
+        description: Option<TicketDescription>,
+        status: Option<Status>,
+    ) -> Result<Self, TicketPatchError> {
+        if title.is_none() && description.is_none() && status.is_none() {
+            return Err(TicketPatchError::MustContainOneField);
+        }
+
+        Ok(Self {
+            title,
+            description,
+            status,
+            ..Default::default()
+        })
+    }
 
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        Self::new(value.to_string())
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.description.is_none()
+            && self.status.is_none()
+            && self.assignee.is_none()
+            && self.priority.is_none()
+            && self.due_date.is_none()
+            && self.add_tags.is_empty()
+            && self.remove_tags.is_empty()
     }
 }
 
-impl PartialEq<&str> for TicketTitle {
-    fn eq(&self, other: &&str) -> bool {
-        self.0 == *other
-    }
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TicketQuery {
+    pub status: Option<Status>,
+    pub assignee: Option<TicketAssignee>,
+    pub tags: BTreeSet<TicketTag>,
+    pub tag_match: TagMatch,
+    // Only tickets past their due date that aren't done yet.
+    pub overdue: bool,
+    pub sort: TicketSort,
+    // Case-insensitive substring, matched against both title and description.
+    pub search: Option<String>,
+    // The first id the page may start from, as returned in `TicketPage::next_cursor`.
+    pub cursor: Option<TicketId>,
+    pub limit: Option<usize>,
+    pub include_archived: bool,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
-pub struct TicketDescription(pub String);
+impl TicketQuery {
+    pub const DEFAULT_LIMIT: usize = 20;
+    pub const MAX_LIMIT: usize = 100;
 
-impl TicketDescription {
-    pub fn new(description: String) -> Result<Self, TicketDescriptionError> {
-        if description.is_empty() {
-            return Err(TicketDescriptionError::Empty);
-        } else if description.len() > TICKET_DESCRIPTION_MAX_LENGTH {
-            return Err(TicketDescriptionError::TooLong(
-                TICKET_DESCRIPTION_MAX_LENGTH,
-            ));
+    pub fn limit(&self) -> usize {
+        self.limit
+            .unwrap_or(Self::DEFAULT_LIMIT)
+            .clamp(1, Self::MAX_LIMIT)
+    }
+
+    // `today` is the date overdue tickets are measured against.
+    pub fn matches(&self, ticket: &Ticket, today: NaiveDate) -> bool {
+        if self.status.is_some_and(|status| status != ticket.status) {
+            return false;
+        }
+
+        if self.assignee.is_some() && self.assignee != ticket.assignee {
+            return false;
+        }
+
+        if !self.tag_match.matches(&self.tags, &ticket.tags) {
+            return false;
+        }
+
+        if self.overdue && !ticket.is_overdue(today) {
+            return false;
+        }
+
+        if let Some(search) = &self.search {
+            let search = search.to_lowercase();
+            return ticket.title.as_str().to_lowercase().contains(&search)
+                || ticket.description.as_str().to_lowercase().contains(&search);
         }
-        Ok(Self(description))
+
+        true
     }
 }
 
-impl TryFrom<String> for TicketDescription {
-    type Error = TicketDescriptionError;
+// The order tickets are listed in. Ties are broken by id.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TicketSort {
+    #[default]
+    Id,
+    // Most urgent first, tickets without a priority last.
+    Priority,
+    // Earliest first, tickets without a due date last.
+    DueDate,
+}
 
-    fn try_from(value: String) -> Result<Self, Self::Error> {
-        Self::new(value)
+impl TicketSort {
+    pub fn compare(&self, a: &Ticket, b: &Ticket) -> Ordering {
+        let ordering = match self {
+            Self::Id => Ordering::Equal,
+            Self::Priority => b.priority.cmp(&a.priority),
+            Self::DueDate => match (a.due_date, b.due_date) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            },
+        };
+        ordering.then(a.id.cmp(&b.id))
     }
 }
 
-impl TryFrom<&str> for TicketDescription {
-    type Error = TicketDescriptionError;
+impl TryFrom<&str> for TicketSort {
+    type Error = ();
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        Self::new(value.to_string())
+        match value.to_lowercase().as_str() {
+            "id" => Ok(Self::Id),
+            "priority" => Ok(Self::Priority),
+            "due_date" => Ok(Self::DueDate),
+            _ => Err(()),
+        }
     }
 }
 
-impl PartialEq<&str> for TicketDescription {
-    fn eq(&self, other: &&str) -> bool {
-        self.0 == *other
-    }
+// How the tags of a query are combined.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagMatch {
+    // The ticket has every tag.
+    #[default]
+    All,
+    // The ticket has at least one of the tags.
+    Any,
 }
 
-#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
-pub struct TicketPatch {
-    pub title: Option<TicketTitle>,
-    pub description: Option<TicketDescription>,
-    pub status: Option<Status>,
+impl TagMatch {
+    // No tags at all match every ticket.
+    pub fn matches(&self, tags: &BTreeSet<TicketTag>, ticket_tags: &BTreeSet<TicketTag>) -> bool {
+        match self {
+            _ if tags.is_empty() => true,
+            Self::All => tags.is_subset(ticket_tags),
+            Self::Any => !tags.is_disjoint(ticket_tags),
+        }
+    }
 }
 
-impl TicketPatch {
-    pub fn new(
-        title: Option<TicketTitle>,
-        description: Option<TicketDescription>,
-        status: Option<Status>,
-    ) -> Result<Self, TicketPatchError> {
-        if title.is_none() && description.is_none() && status.is_none() {
-            return Err(TicketPatchError::MustContainOneField);
-        }
+impl TryFrom<&str> for TagMatch {
+    type Error = ();
 
-        Ok(Self {
-            title,
-            description,
-            status,
-        })
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "all" => Ok(Self::All),
+            "any" => Ok(Self::Any),
+            _ => Err(()),
+        }
     }
 }
 
-pub fn validate_ticket(ticket: &Ticket) -> Result<(), AppError> {
-    if ticket.title.0.is_empty() {
-        return Err(TicketTitleError::Empty.into());
-    } else if ticket.title.0.len() > TICKET_TITLE_MAX_LENGTH {
-        return Err(TicketTitleError::TooLong(TICKET_TITLE_MAX_LENGTH).into());
-    }
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    // Any of its words has to appear in the title or the description.
+    pub text: String,
+    pub limit: Option<usize>,
+    pub include_archived: bool,
+}
 
-    if ticket.description.0.is_empty() {
-        return Err(TicketDescriptionError::Empty.into());
-    } else if ticket.description.0.len() > TICKET_DESCRIPTION_MAX_LENGTH {
-        return Err(TicketDescriptionError::TooLong(TICKET_DESCRIPTION_MAX_LENGTH).into());
+impl SearchQuery {
+    pub fn limit(&self) -> usize {
+        self.limit
+            .unwrap_or(TicketQuery::DEFAULT_LIMIT)
+            .clamp(1, TicketQuery::MAX_LIMIT)
     }
+}
 
-    Ok(())
+// Best match first.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+#[salvo(schema(symbol = "SearchHit"))]
+pub struct SearchHit {
+    pub ticket: Ticket,
+    pub score: f64,
 }
 
-pub fn validate_ticket_draft(ticket_draft: &TicketDraft) -> Result<(), AppError> {
-    if ticket_draft.title.0.is_empty() {
-        return Err(TicketTitleError::Empty.into());
-    } else if ticket_draft.title.0.len() > TICKET_TITLE_MAX_LENGTH {
-        return Err(TicketTitleError::TooLong(TICKET_TITLE_MAX_LENGTH).into());
-    }
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+#[salvo(schema(symbol = "TicketPage"))]
+pub struct TicketPage {
+    pub tickets: Vec<Ticket>,
+    pub next_cursor: Option<TicketId>,
+}
 
-    if ticket_draft.description.0.is_empty() {
-        return Err(TicketDescriptionError::Empty.into());
-    } else if ticket_draft.description.0.len() > TICKET_DESCRIPTION_MAX_LENGTH {
-        return Err(TicketDescriptionError::TooLong(TICKET_DESCRIPTION_MAX_LENGTH).into());
+pub fn validate_ticket(ticket: &Ticket, policy: &ValidationPolicy) -> Result<(), AppError> {
+    TicketTitle::new(ticket.title.as_str(), policy)?;
+    TicketDescription::new(ticket.description.as_str(), policy)?;
+    for user in ticket.assignee.iter().chain(&ticket.created_by) {
+        TicketAssignee::new(user.as_str(), policy)?;
+    }
+    for tag in &ticket.tags {
+        TicketTag::new(tag.as_str(), policy)?;
     }
 
     Ok(())
 }
 
-pub fn validate_ticket_patch(ticket_patch: &TicketPatch) -> Result<(), AppError> {
-    if ticket_patch.title.is_none()
-        && ticket_patch.description.is_none()
-        && ticket_patch.status.is_none()
-    {
+// Returns the draft normalized according to `policy`.
+pub fn validate_ticket_draft(
+    ticket_draft: TicketDraft,
+    policy: &ValidationPolicy,
+) -> Result<TicketDraft, AppError> {
+    Ok(TicketDraft {
+        title: TicketTitle::new(ticket_draft.title.as_str(), policy)?,
+        description: TicketDescription::new(ticket_draft.description.as_str(), policy)?,
+        assignee: ticket_draft
+            .assignee
+            .map(|assignee| TicketAssignee::new(assignee.as_str(), policy))
+            .transpose()?,
+        tags: validate_tags(ticket_draft.tags, policy)?,
+        priority: ticket_draft.priority,
+        due_date: ticket_draft.due_date,
+        created_by: ticket_draft.created_by,
+    })
+}
+
+// Patches are validated against the ticket they are applied to,
+// since whether a status change is allowed depends on the current status.
+// Returns the patch normalized according to `policy`.
+pub fn validate_ticket_patch(
+    ticket_patch: TicketPatch,
+    ticket: &Ticket,
+    workflow: &StatusWorkflow,
+    policy: &ValidationPolicy,
+) -> Result<TicketPatch, AppError> {
+    if ticket_patch.is_empty() {
         return Err(TicketPatchError::MustContainOneField.into());
     }
 
-    if let Some(title) = &ticket_patch.title {
-        if title.0.is_empty() {
-            return Err(TicketTitleError::Empty.into());
-        } else if title.0.len() > TICKET_TITLE_MAX_LENGTH {
-            return Err(TicketTitleError::TooLong(TICKET_TITLE_MAX_LENGTH).into());
-        }
+    if let Some(status) = ticket_patch.status {
+        workflow.check(ticket.status, status)?;
     }
 
-    if let Some(description) = &ticket_patch.description {
-        if description.0.is_empty() {
-            return Err(TicketDescriptionError::Empty.into());
-        } else if description.0.len() > TICKET_DESCRIPTION_MAX_LENGTH {
-            return Err(TicketDescriptionError::TooLong(TICKET_DESCRIPTION_MAX_LENGTH).into());
-        }
+    if let Some(tag) = ticket_patch
+        .add_tags
+        .intersection(&ticket_patch.remove_tags)
+        .next()
+    {
+        return Err(TicketPatchError::TagAddedAndRemoved(tag.clone()).into());
     }
 
-    Ok(())
+    Ok(TicketPatch {
+        title: ticket_patch
+            .title
+            .map(|title| TicketTitle::new(title.as_str(), policy))
+            .transpose()?,
+        description: ticket_patch
+            .description
+            .map(|description| TicketDescription::new(description.as_str(), policy))
+            .transpose()?,
+        status: ticket_patch.status,
+        assignee: ticket_patch
+            .assignee
+            .map(|assignee| {
+                assignee
+                    .map(|assignee| TicketAssignee::new(assignee.as_str(), policy))
+                    .transpose()
+            })
+            .transpose()?,
+        priority: ticket_patch.priority,
+        due_date: ticket_patch.due_date,
+        add_tags: validate_tags(ticket_patch.add_tags, policy)?,
+        remove_tags: ticket_patch.remove_tags,
+    })
+}
+
+fn validate_tags(
+    tags: BTreeSet<TicketTag>,
+    policy: &ValidationPolicy,
+) -> Result<BTreeSet<TicketTag>, AppError> {
+    tags.into_iter()
+        .map(|tag| TicketTag::new(tag.as_str(), policy).map_err(AppError::from))
+        .collect()
 }
@@ -1,7 +1,18 @@
+use salvo::http::header::{HeaderValue, RETRY_AFTER, WWW_AUTHENTICATE};
+use salvo::oapi::{self, Components, EndpointOutRegister, Operation, ToSchema};
 use salvo::prelude::*;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use ticket_fields::FieldError;
+
+use crate::data::{Status, TicketTag};
+use crate::store::TicketId;
+
+pub use ticket_fields::{
+    CommentBodyError, TicketAssigneeError, TicketDescriptionError, TicketTagError, TicketTitleError,
+};
+
 #[derive(Debug, Error)]
 pub enum ServerError {
     #[error("Server error: {0}")]
@@ -10,6 +21,35 @@ pub enum ServerError {
     IoError(#[from] std::io::Error),
 }
 
+// What `client::TicketClient` returns when a request fails.
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("HTTP error: {0}")]
+    HttpError(#[from] reqwest::Error),
+    // The server answered with an `AppError`.
+    #[error("{} ({status})", .error.error)]
+    Api {
+        status: StatusCode,
+        error: AppErrorWriter,
+    },
+    #[error("Unexpected response: {0}")]
+    UnexpectedResponse(StatusCode),
+    // Paths can't be joined to the base URL, e.g. `mailto:x`.
+    #[error("Invalid base URL: {0}")]
+    InvalidBaseUrl(reqwest::Url),
+}
+
+impl ClientError {
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            Self::Api { error, .. } => Some(error.code),
+            _ => None,
+        }
+    }
+}
+
+pub type ClientResult<T> = Result<T, ClientError>;
+
 #[derive(Debug, Error)]
 #[non_exhaustive]
 pub enum AppError {
@@ -21,70 +61,474 @@ pub enum AppError {
     TicketTitleError(#[from] TicketTitleError),
     #[error("Ticket description error: {0}")]
     TicketDescriptionError(#[from] TicketDescriptionError),
+    #[error("Ticket assignee error: {0}")]
+    TicketAssigneeError(#[from] TicketAssigneeError),
+    #[error("Ticket tag error: {0}")]
+    TicketTagError(#[from] TicketTagError),
+    #[error("Comment error: {0}")]
+    CommentBodyError(#[from] CommentBodyError),
     #[error("{0}")]
-    InvalidTicketStatus(String),
+    TicketStatusError(#[from] TicketStatusError),
     #[error("Failed to serialize response")]
     SerializationError(#[from] serde_json::Error),
     #[error("Failed to parse JSON: {0}")]
     JsonParseError(#[from] salvo::http::ParseError),
     #[error("{0}")]
     TicketPatchError(#[from] TicketPatchError),
+    #[error("{0}")]
+    TicketLinkError(#[from] TicketLinkError),
 
     #[error("Lock is poisoned")]
     PoisonError,
     #[error("Ticket store not initialized")]
     TicketStoreNotInitialized,
+    #[error("Missing or unknown bearer token")]
+    Unauthorized,
+    #[error("Not allowed to do this")]
+    Forbidden,
+    #[error("Too many requests, retry in {retry_after} seconds")]
+    TooManyRequests { retry_after: u64 },
+    #[error("Request body is larger than {max} bytes")]
+    PayloadTooLarge { max: usize },
     #[error("Ticket not found")]
     NotTicket,
+    #[error("Ticket is already archived")]
+    TicketArchived,
+    #[error("Link not found")]
+    NotLink,
+    #[error("Ticket is blocked by unresolved tickets: {}", blocker_list(.0))]
+    TicketBlocked(Vec<TicketId>),
+    #[error("Not applied, since another item of the batch failed")]
+    BatchRejected,
+    #[error("Ticket version mismatch: expected {expected}, but the ticket is at {actual}")]
+    VersionMismatch { expected: u64, actual: u64 },
+    #[error("Invalid If-Match header")]
+    InvalidIfMatch,
     #[error("Invalid ticket ID")]
     InvalidTicketId,
+    #[error("Invalid query parameter: {0}")]
+    InvalidQueryParameter(String),
 }
 
 pub type AppResult<T> = Result<T, AppError>;
 
-#[derive(Serialize)]
+fn blocker_list(blockers: &[TicketId]) -> String {
+    blockers
+        .iter()
+        .map(|id| id.0.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// A stable, machine-readable identifier for each kind of failure,
+// so that clients don't have to match on the error message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[salvo(schema(symbol = "ErrorCode"))]
+pub enum ErrorCode {
+    ServerError,
+    IoError,
+    InvalidTitle,
+    InvalidDescription,
+    InvalidAssignee,
+    InvalidTag,
+    InvalidComment,
+    InvalidStatus,
+    IllegalStatusTransition,
+    SerializationError,
+    InvalidJson,
+    InvalidPatch,
+    InvalidLink,
+    PoisonedLock,
+    StoreNotInitialized,
+    Unauthorized,
+    Forbidden,
+    TooManyRequests,
+    PayloadTooLarge,
+    TicketNotFound,
+    TicketArchived,
+    LinkNotFound,
+    TicketBlocked,
+    BatchRejected,
+    VersionMismatch,
+    InvalidIfMatch,
+    InvalidTicketId,
+    InvalidQueryParameter,
+}
+
+impl AppError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::TicketTitleError(_)
+            | Self::TicketDescriptionError(_)
+            | Self::TicketAssigneeError(_)
+            | Self::TicketTagError(_)
+            | Self::CommentBodyError(_)
+            | Self::TicketStatusError(_)
+            | Self::TicketPatchError(_)
+            | Self::TicketLinkError(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::JsonParseError(_)
+            | Self::InvalidIfMatch
+            | Self::InvalidTicketId
+            | Self::InvalidQueryParameter(_) => StatusCode::BAD_REQUEST,
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::Forbidden => StatusCode::FORBIDDEN,
+            Self::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Self::PayloadTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::NotTicket | Self::NotLink => StatusCode::NOT_FOUND,
+            Self::TicketArchived | Self::TicketBlocked(_) => StatusCode::CONFLICT,
+            Self::VersionMismatch { .. } => StatusCode::PRECONDITION_FAILED,
+            Self::BatchRejected => StatusCode::FAILED_DEPENDENCY,
+            Self::ServerError(_)
+            | Self::IoError(_)
+            | Self::SerializationError(_)
+            | Self::PoisonError
+            | Self::TicketStoreNotInitialized => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::ServerError(_) => ErrorCode::ServerError,
+            Self::IoError(_) => ErrorCode::IoError,
+            Self::TicketTitleError(_) => ErrorCode::InvalidTitle,
+            Self::TicketDescriptionError(_) => ErrorCode::InvalidDescription,
+            Self::TicketAssigneeError(_) => ErrorCode::InvalidAssignee,
+            Self::TicketTagError(_) => ErrorCode::InvalidTag,
+            Self::CommentBodyError(_) => ErrorCode::InvalidComment,
+            Self::TicketStatusError(TicketStatusError::Invalid(_)) => ErrorCode::InvalidStatus,
+            Self::TicketStatusError(TicketStatusError::IllegalTransition { .. }) => {
+                ErrorCode::IllegalStatusTransition
+            }
+            Self::SerializationError(_) => ErrorCode::SerializationError,
+            Self::JsonParseError(_) => ErrorCode::InvalidJson,
+            Self::TicketPatchError(_) => ErrorCode::InvalidPatch,
+            Self::TicketLinkError(_) => ErrorCode::InvalidLink,
+            Self::PoisonError => ErrorCode::PoisonedLock,
+            Self::TicketStoreNotInitialized => ErrorCode::StoreNotInitialized,
+            Self::Unauthorized => ErrorCode::Unauthorized,
+            Self::Forbidden => ErrorCode::Forbidden,
+            Self::TooManyRequests { .. } => ErrorCode::TooManyRequests,
+            Self::PayloadTooLarge { .. } => ErrorCode::PayloadTooLarge,
+            Self::NotTicket => ErrorCode::TicketNotFound,
+            Self::TicketArchived => ErrorCode::TicketArchived,
+            Self::NotLink => ErrorCode::LinkNotFound,
+            Self::TicketBlocked(_) => ErrorCode::TicketBlocked,
+            Self::BatchRejected => ErrorCode::BatchRejected,
+            Self::VersionMismatch { .. } => ErrorCode::VersionMismatch,
+            Self::InvalidIfMatch => ErrorCode::InvalidIfMatch,
+            Self::InvalidTicketId => ErrorCode::InvalidTicketId,
+            Self::InvalidQueryParameter(_) => ErrorCode::InvalidQueryParameter,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[salvo(schema(symbol = "AppErrorWriter"))]
 pub struct AppErrorWriter {
-    error: String,
+    pub code: ErrorCode,
+    pub error: String,
+}
+
+impl From<FieldError> for AppError {
+    fn from(error: FieldError) -> Self {
+        match error {
+            FieldError::Title(e) => e.into(),
+            FieldError::Description(e) => e.into(),
+            FieldError::Assignee(e) => e.into(),
+            FieldError::Tag(e) => e.into(),
+            FieldError::Comment(e) => e.into(),
+        }
+    }
+}
+
+impl From<&AppError> for AppErrorWriter {
+    fn from(error: &AppError) -> Self {
+        Self {
+            code: error.code(),
+            error: error.to_string(),
+        }
+    }
+}
+
+// Documents the error responses of every endpoint. Each endpoint narrows them
+// down with `status_codes(...)` to the ones it can actually return.
+impl EndpointOutRegister for AppError {
+    fn register(components: &mut Components, operation: &mut Operation) {
+        for status_code in [
+            StatusCode::BAD_REQUEST,
+            StatusCode::UNAUTHORIZED,
+            StatusCode::FORBIDDEN,
+            StatusCode::NOT_FOUND,
+            StatusCode::CONFLICT,
+            StatusCode::PRECONDITION_FAILED,
+            StatusCode::PAYLOAD_TOO_LARGE,
+            StatusCode::UNPROCESSABLE_ENTITY,
+            StatusCode::FAILED_DEPENDENCY,
+            StatusCode::TOO_MANY_REQUESTS,
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ] {
+            let response = oapi::Response::new(status_code.canonical_reason().unwrap_or_default())
+                .add_content("application/json", AppErrorWriter::to_schema(components));
+            operation.responses.insert(status_code.as_str(), response);
+        }
+    }
 }
 
 #[async_trait]
 impl Writer for AppError {
     async fn write(mut self, _req: &mut Request, _depot: &mut Depot, res: &mut Response) {
-        let output = AppErrorWriter {
-            error: self.to_string(),
-        };
+        let output = AppErrorWriter::from(&self);
         let err = serde_json::to_string(&output).unwrap_or_else(|_| {
-            serde_json::json!({"error": "Failed to serialize error"}).to_string()
+            serde_json::json!({"code": "serialization_error", "error": "Failed to serialize error"})
+                .to_string()
         });
 
+        match self {
+            Self::Unauthorized => {
+                res.headers_mut()
+                    .insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            }
+            Self::TooManyRequests { retry_after } => {
+                res.headers_mut().insert(RETRY_AFTER, retry_after.into());
+            }
+            _ => {}
+        }
+        res.status_code(self.status_code());
         res.render(Text::Json(err));
     }
 }
 
 #[derive(Debug, Error)]
-pub enum TicketTitleError {
-    #[error("The title cannot be empty")]
-    Empty,
-    #[error("The title cannot be longer than {0} characters")]
-    TooLong(usize),
-}
-
-#[derive(Debug, Error)]
-pub enum TicketDescriptionError {
-    #[error("The description cannot be empty")]
-    Empty,
-    #[error("The description cannot be longer than {0} characters")]
-    TooLong(usize),
+pub enum TicketStatusError {
+    #[error("Invalid ticket status: {0}")]
+    Invalid(String),
+    #[error("Illegal status transition from {from:?} to {to:?}")]
+    IllegalTransition { from: Status, to: Status },
 }
 
 #[derive(Debug, Error)]
-#[error("Invalid ticket status: {0}")]
-pub enum TicketStatusError {
-    Invalid(String),
+pub enum TicketLinkError {
+    #[error("A ticket cannot be linked to itself")]
+    SelfLink,
+    #[error("The tickets are already linked")]
+    AlreadyLinked,
+    #[error("The ticket already has a parent: {}", (.0).0)]
+    AlreadyHasParent(TicketId),
+    #[error("The link would create a cycle")]
+    Cycle,
 }
 
 #[derive(Debug, Error)]
 pub enum TicketPatchError {
     #[error("At least one field must be present")]
     MustContainOneField,
+    #[error("The tag {} cannot be both added and removed", .0.as_str())]
+    TagAddedAndRemoved(TicketTag),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use salvo::http::ResBody;
+
+    fn io_error() -> std::io::Error {
+        std::io::Error::other("disk is gone")
+    }
+
+    fn json_error() -> serde_json::Error {
+        serde_json::from_str::<serde_json::Value>("{").unwrap_err()
+    }
+
+    #[test]
+    fn test_status_and_code_of_every_variant() {
+        let cases = [
+            (
+                AppError::ServerError(salvo::Error::other("boom")),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorCode::ServerError,
+            ),
+            (
+                AppError::IoError(io_error()),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorCode::IoError,
+            ),
+            (
+                TicketTitleError::Empty.into(),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorCode::InvalidTitle,
+            ),
+            (
+                TicketDescriptionError::TooLong {
+                    max: 500,
+                    actual: 501,
+                }
+                .into(),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorCode::InvalidDescription,
+            ),
+            (
+                TicketAssigneeError::Empty.into(),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorCode::InvalidAssignee,
+            ),
+            (
+                TicketTagError::Whitespace.into(),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorCode::InvalidTag,
+            ),
+            (
+                CommentBodyError::Empty.into(),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorCode::InvalidComment,
+            ),
+            (
+                TicketStatusError::Invalid("Closed".into()).into(),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorCode::InvalidStatus,
+            ),
+            (
+                TicketStatusError::IllegalTransition {
+                    from: Status::ToDo,
+                    to: Status::Done,
+                }
+                .into(),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorCode::IllegalStatusTransition,
+            ),
+            (
+                AppError::SerializationError(json_error()),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorCode::SerializationError,
+            ),
+            (
+                AppError::JsonParseError(salvo::http::ParseError::EmptyBody),
+                StatusCode::BAD_REQUEST,
+                ErrorCode::InvalidJson,
+            ),
+            (
+                TicketPatchError::MustContainOneField.into(),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorCode::InvalidPatch,
+            ),
+            (
+                TicketPatchError::TagAddedAndRemoved(TicketTag::try_from("backend").unwrap())
+                    .into(),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorCode::InvalidPatch,
+            ),
+            (
+                AppError::PoisonError,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorCode::PoisonedLock,
+            ),
+            (
+                AppError::TicketStoreNotInitialized,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorCode::StoreNotInitialized,
+            ),
+            (
+                AppError::Unauthorized,
+                StatusCode::UNAUTHORIZED,
+                ErrorCode::Unauthorized,
+            ),
+            (
+                AppError::Forbidden,
+                StatusCode::FORBIDDEN,
+                ErrorCode::Forbidden,
+            ),
+            (
+                AppError::TooManyRequests { retry_after: 1 },
+                StatusCode::TOO_MANY_REQUESTS,
+                ErrorCode::TooManyRequests,
+            ),
+            (
+                AppError::PayloadTooLarge { max: 1024 },
+                StatusCode::PAYLOAD_TOO_LARGE,
+                ErrorCode::PayloadTooLarge,
+            ),
+            (
+                AppError::NotTicket,
+                StatusCode::NOT_FOUND,
+                ErrorCode::TicketNotFound,
+            ),
+            (
+                AppError::TicketArchived,
+                StatusCode::CONFLICT,
+                ErrorCode::TicketArchived,
+            ),
+            (
+                AppError::NotLink,
+                StatusCode::NOT_FOUND,
+                ErrorCode::LinkNotFound,
+            ),
+            (
+                AppError::TicketBlocked(vec![TicketId(1), TicketId(2)]),
+                StatusCode::CONFLICT,
+                ErrorCode::TicketBlocked,
+            ),
+            (
+                AppError::BatchRejected,
+                StatusCode::FAILED_DEPENDENCY,
+                ErrorCode::BatchRejected,
+            ),
+            (
+                TicketLinkError::Cycle.into(),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorCode::InvalidLink,
+            ),
+            (
+                AppError::VersionMismatch {
+                    expected: 1,
+                    actual: 2,
+                },
+                StatusCode::PRECONDITION_FAILED,
+                ErrorCode::VersionMismatch,
+            ),
+            (
+                AppError::InvalidIfMatch,
+                StatusCode::BAD_REQUEST,
+                ErrorCode::InvalidIfMatch,
+            ),
+            (
+                AppError::InvalidTicketId,
+                StatusCode::BAD_REQUEST,
+                ErrorCode::InvalidTicketId,
+            ),
+            (
+                AppError::InvalidQueryParameter("limit".into()),
+                StatusCode::BAD_REQUEST,
+                ErrorCode::InvalidQueryParameter,
+            ),
+        ];
+
+        for (error, status_code, code) in cases {
+            assert_eq!(error.status_code(), status_code, "{error:?}");
+            assert_eq!(error.code(), code, "{error:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_writer_renders_status_and_body() {
+        let mut req = Request::new();
+        let mut depot = Depot::new();
+        let mut res = Response::new();
+
+        AppError::NotTicket
+            .write(&mut req, &mut depot, &mut res)
+            .await;
+
+        assert_eq!(res.status_code, Some(StatusCode::NOT_FOUND));
+        let ResBody::Once(body) = res.take_body() else {
+            panic!("expected a single chunk body");
+        };
+        let output: AppErrorWriter = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            output,
+            AppErrorWriter {
+                code: ErrorCode::TicketNotFound,
+                error: "Ticket not found".into(),
+            }
+        );
+    }
 }
@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+use salvo::oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::data::{Status, Ticket};
+use crate::store::TicketId;
+
+// How many events a subscriber can fall behind by before it starts missing some.
+// Writers never wait for subscribers: a lagging one skips the oldest events instead.
+pub const FEED_CAPACITY: usize = 256;
+
+// Published by the store after a ticket is created or patched,
+// with the ticket as it is after the change.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(tag = "event", rename_all = "snake_case")]
+#[salvo(schema(symbol = "TicketEvent"))]
+pub enum TicketEvent {
+    Created {
+        ticket: Ticket,
+        #[salvo(schema(value_type = String, format = DateTime))]
+        at: DateTime<Utc>,
+    },
+    Patched {
+        ticket: Ticket,
+        #[salvo(schema(value_type = String, format = DateTime))]
+        at: DateTime<Utc>,
+    },
+}
+
+impl TicketEvent {
+    pub fn ticket(&self) -> &Ticket {
+        match self {
+            Self::Created { ticket, .. } | Self::Patched { ticket, .. } => ticket,
+        }
+    }
+
+    // The name of the event in an SSE stream.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Created { .. } => "created",
+            Self::Patched { .. } => "patched",
+        }
+    }
+}
+
+// Which events a subscriber is interested in. Unset fields match anything.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EventFilter {
+    pub id: Option<TicketId>,
+    // Matched against the status after the change.
+    pub status: Option<Status>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &TicketEvent) -> bool {
+        let ticket = event.ticket();
+        self.id.is_none_or(|id| ticket.id == id)
+            && self.status.is_none_or(|status| ticket.status == status)
+    }
+}
+
+pub fn channel() -> broadcast::Sender<TicketEvent> {
+    broadcast::channel(FEED_CAPACITY).0
+}
@@ -0,0 +1,77 @@
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use salvo::oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::data::{Priority, Status, TicketAssignee, TicketDescription, TicketTag, TicketTitle};
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(tag = "change", rename_all = "snake_case")]
+#[salvo(schema(symbol = "TicketChange"))]
+pub enum TicketChange {
+    Created {
+        #[salvo(schema(value_type = String))]
+        title: TicketTitle,
+        #[salvo(schema(value_type = String))]
+        description: TicketDescription,
+        #[serde(default)]
+        #[salvo(schema(value_type = Option<String>))]
+        assignee: Option<TicketAssignee>,
+        #[serde(default)]
+        #[salvo(schema(value_type = Vec<String>))]
+        tags: BTreeSet<TicketTag>,
+        #[serde(default)]
+        priority: Option<Priority>,
+        #[serde(default)]
+        #[salvo(schema(value_type = Option<String>, format = Date))]
+        due_date: Option<NaiveDate>,
+    },
+    TitleChanged {
+        #[salvo(schema(value_type = String))]
+        from: TicketTitle,
+        #[salvo(schema(value_type = String))]
+        to: TicketTitle,
+    },
+    DescriptionChanged {
+        #[salvo(schema(value_type = String))]
+        from: TicketDescription,
+        #[salvo(schema(value_type = String))]
+        to: TicketDescription,
+    },
+    StatusChanged {
+        from: Status,
+        to: Status,
+    },
+    AssigneeChanged {
+        #[salvo(schema(value_type = Option<String>))]
+        from: Option<TicketAssignee>,
+        #[salvo(schema(value_type = Option<String>))]
+        to: Option<TicketAssignee>,
+    },
+    PriorityChanged {
+        from: Option<Priority>,
+        to: Option<Priority>,
+    },
+    DueDateChanged {
+        #[salvo(schema(value_type = Option<String>, format = Date))]
+        from: Option<NaiveDate>,
+        #[salvo(schema(value_type = Option<String>, format = Date))]
+        to: Option<NaiveDate>,
+    },
+    TagsChanged {
+        #[salvo(schema(value_type = Vec<String>))]
+        added: BTreeSet<TicketTag>,
+        #[salvo(schema(value_type = Vec<String>))]
+        removed: BTreeSet<TicketTag>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+#[salvo(schema(symbol = "HistoryEntry"))]
+pub struct HistoryEntry {
+    #[salvo(schema(value_type = String, format = DateTime))]
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub change: TicketChange,
+}
@@ -0,0 +1,185 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::data::{TagMatch, Ticket, TicketTag};
+use crate::store::TicketId;
+
+// Maps every tag to the tickets carrying it,
+// so that tag queries don't have to look at every ticket.
+#[derive(Debug, Default)]
+pub struct TagIndex {
+    tickets: BTreeMap<TicketTag, BTreeSet<TicketId>>,
+}
+
+impl TagIndex {
+    pub fn insert<'a>(&mut self, id: TicketId, tags: impl IntoIterator<Item = &'a TicketTag>) {
+        for tag in tags {
+            self.tickets.entry(tag.clone()).or_default().insert(id);
+        }
+    }
+
+    pub fn remove<'a>(&mut self, id: TicketId, tags: impl IntoIterator<Item = &'a TicketTag>) {
+        for tag in tags {
+            if let Some(ids) = self.tickets.get_mut(tag) {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    self.tickets.remove(tag);
+                }
+            }
+        }
+    }
+
+    // The tickets matching `tags`, or `None` if there are no tags to filter on.
+    pub fn lookup(
+        &self,
+        tags: &BTreeSet<TicketTag>,
+        tag_match: TagMatch,
+    ) -> Option<BTreeSet<TicketId>> {
+        let mut ids = tags
+            .iter()
+            .map(|tag| self.tickets.get(tag).cloned().unwrap_or_default());
+        let first = ids.next()?;
+
+        Some(match tag_match {
+            TagMatch::All => ids.fold(first, |acc, ids| &acc & &ids),
+            TagMatch::Any => ids.fold(first, |acc, ids| &acc | &ids),
+        })
+    }
+}
+
+// An inverted index from the words in ticket titles and descriptions
+// to the tickets they appear in.
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    // How often each term occurs in each ticket, title occurrences counting double.
+    postings: BTreeMap<String, BTreeMap<TicketId, u32>>,
+    terms: BTreeMap<TicketId, BTreeSet<String>>,
+}
+
+const TITLE_WEIGHT: u32 = 2;
+
+// Words are runs of alphanumeric characters, compared case-insensitively.
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+impl SearchIndex {
+    // Replaces whatever was indexed for the ticket.
+    pub fn insert(&mut self, ticket: &Ticket) {
+        self.remove(ticket.id);
+
+        let mut frequencies = BTreeMap::<String, u32>::new();
+        for term in tokenize(ticket.title.as_str()) {
+            *frequencies.entry(term).or_default() += TITLE_WEIGHT;
+        }
+        for term in tokenize(ticket.description.as_str()) {
+            *frequencies.entry(term).or_default() += 1;
+        }
+
+        let terms = self.terms.entry(ticket.id).or_default();
+        for (term, frequency) in frequencies {
+            terms.insert(term.clone());
+            self.postings
+                .entry(term)
+                .or_default()
+                .insert(ticket.id, frequency);
+        }
+    }
+
+    pub fn remove(&mut self, id: TicketId) {
+        for term in self.terms.remove(&id).into_iter().flatten() {
+            if let Some(tickets) = self.postings.get_mut(&term) {
+                tickets.remove(&id);
+                if tickets.is_empty() {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+    }
+
+    // Tickets containing any of the terms of `query`, best match first.
+    // A term scores its frequency in the ticket, weighted by how rare it is
+    // across tickets (tf-idf), so tickets matching more and rarer terms rank higher.
+    pub fn search(&self, query: &str) -> Vec<(TicketId, f64)> {
+        let total = self.terms.len() as f64;
+        let terms: BTreeSet<_> = tokenize(query).collect();
+
+        let mut scores = BTreeMap::<TicketId, f64>::new();
+        for term in &terms {
+            let Some(tickets) = self.postings.get(term) else {
+                continue;
+            };
+            let idf = (1.0 + total / tickets.len() as f64).ln();
+            for (id, frequency) in tickets {
+                *scores.entry(*id).or_default() += f64::from(*frequency) * idf;
+            }
+        }
+
+        let mut hits: Vec<_> = scores.into_iter().collect();
+        hits.sort_by(|(a_id, a), (b_id, b)| b.total_cmp(a).then(a_id.cmp(b_id)));
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TicketDraft;
+
+    fn ticket(id: u64, title: &str, description: &str) -> Ticket {
+        let draft = TicketDraft::new(title.try_into().unwrap(), description.try_into().unwrap());
+        Ticket::from_draft(TicketId(id), draft)
+    }
+
+    fn tags(tags: &[&str]) -> BTreeSet<TicketTag> {
+        tags.iter().map(|tag| (*tag).try_into().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_lookup() {
+        let mut index = TagIndex::default();
+        index.insert(TicketId(0), &tags(&["backend", "bug"]));
+        index.insert(TicketId(1), &tags(&["backend"]));
+        index.insert(TicketId(2), &tags(&["frontend"]));
+
+        let all = index.lookup(&tags(&["backend", "bug"]), TagMatch::All);
+        assert_eq!(all, Some(BTreeSet::from([TicketId(0)])));
+
+        let any = index.lookup(&tags(&["bug", "frontend"]), TagMatch::Any);
+        assert_eq!(any, Some(BTreeSet::from([TicketId(0), TicketId(2)])));
+
+        assert_eq!(index.lookup(&tags(&[]), TagMatch::All), None);
+
+        index.remove(TicketId(0), &tags(&["bug"]));
+        let all = index.lookup(&tags(&["backend", "bug"]), TagMatch::All);
+        assert_eq!(all, Some(BTreeSet::new()));
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let terms: Vec<_> = tokenize("Fix the LOGIN page, again!").collect();
+        assert_eq!(terms, ["fix", "the", "login", "page", "again"]);
+    }
+
+    #[test]
+    fn test_search_ranks_matches() {
+        let mut index = SearchIndex::default();
+        index.insert(&ticket(0, "Fix login", "The login page crashes"));
+        index.insert(&ticket(1, "Write docs", "Document the login flow"));
+        index.insert(&ticket(2, "Fix signup", "Nothing to do with it"));
+
+        let ids =
+            |hits: Vec<(TicketId, f64)>| hits.into_iter().map(|(id, _)| id.0).collect::<Vec<_>>();
+        assert_eq!(ids(index.search("LOGIN")), [0, 1]);
+        assert_eq!(ids(index.search("fix login")), [0, 2, 1]);
+        assert!(index.search("logout").is_empty());
+
+        index.insert(&ticket(0, "Fix logout", "The logout button is gone"));
+        assert_eq!(ids(index.search("login")), [1]);
+        assert_eq!(ids(index.search("logout")), [0]);
+
+        index.remove(TicketId(1));
+        assert!(index.search("login").is_empty());
+    }
+}
@@ -9,28 +9,83 @@
 // Use Rust's package registry, crates.io, to find the dependencies you need
 // (if any) to build this system.
 
+pub mod auth;
+pub mod bulk;
+pub mod client;
+pub mod clock;
+pub mod comment;
+pub mod config;
 pub mod data;
 pub mod error;
+pub mod feed;
+pub mod history;
+pub mod index;
+pub mod limit;
+pub mod link;
 pub mod server;
+pub mod storage;
 pub mod store;
+pub mod transfer;
+pub mod workflow;
 
 #[cfg(test)]
 mod test {
     use crate::*;
     use serde::{Deserialize, Serialize};
-
-    const LOCAL_ADDR: &str = "127.0.0.1:5800";
+    use std::sync::Arc;
+    use tokio::sync::{oneshot, RwLock};
+    use tokio::task::JoinHandle;
 
     #[derive(Debug, Serialize, Deserialize)]
     struct CreateResponse {
         id: u64,
     }
 
+    struct TestServer {
+        base_url: reqwest::Url,
+        shutdown: oneshot::Sender<()>,
+        handle: JoinHandle<Result<(), error::ServerError>>,
+    }
+
+    impl TestServer {
+        async fn stop(self) -> Result<(), error::ServerError> {
+            self.shutdown.send(()).unwrap();
+            self.handle.await.unwrap()
+        }
+    }
+
+    async fn spawn_server(store: store::TicketStore) -> TestServer {
+        spawn_server_with_config(store, config::ServerConfig::default()).await
+    }
+
+    async fn spawn_server_with_config(
+        store: store::TicketStore,
+        config: config::ServerConfig,
+    ) -> TestServer {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+        let store = Arc::new(RwLock::new(store));
+
+        let (shutdown, shutdown_rx) = oneshot::channel();
+        let handle = tokio::spawn(async move {
+            server::run_with_config(listener, store, &config, async {
+                shutdown_rx.await.ok();
+            })
+            .await
+        });
+        let base_url = format!("http://{local_addr}/api/ticket").parse().unwrap();
+
+        TestServer {
+            base_url,
+            shutdown,
+            handle,
+        }
+    }
+
     #[tokio::test]
     async fn test_serve() {
-        let server_handle = tokio::spawn(server::run(LOCAL_ADDR));
-
-        let base_url: reqwest::Url = format!("http://{LOCAL_ADDR}/api/ticket").parse().unwrap();
+        let server = spawn_server(store::TicketStore::new()).await;
+        let base_url = server.base_url.clone();
         let client = reqwest::Client::new();
 
         let src_data = r#"{ "title": "Test Title", "description": "Test Description" }"#;
@@ -75,6 +130,68 @@ mod test {
         let data: data::Ticket = res.json().await.unwrap();
         assert_eq!(data.status, data::Status::InProgress);
 
+        let res = client
+            .get(base_url.clone())
+            .query(&[("status", "inprogress"), ("search", "test")])
+            .send()
+            .await
+            .unwrap();
+
+        let page: data::TicketPage = res.json().await.unwrap();
+        assert_eq!(page.tickets.len(), 1);
+        assert_eq!(page.tickets[0].id.0, create_result_data.id);
+        assert_eq!(page.next_cursor, None);
+
+        let res = client
+            .patch(
+                base_url
+                    .join(&format!("ticket/{}", create_result_data.id))
+                    .unwrap(),
+            )
+            .body(r#"{ "assignee": "Alice" }"#)
+            .header("Content-Type", "application/json")
+            .send()
+            .await
+            .unwrap();
+        let data: data::Ticket = res.json().await.unwrap();
+        assert_eq!(data.assignee.unwrap(), "Alice");
+
+        for (assignee, expected) in [("Alice", 1), ("Bob", 0)] {
+            let res = client
+                .get(base_url.clone())
+                .query(&[("assignee", assignee)])
+                .send()
+                .await
+                .unwrap();
+            let page: data::TicketPage = res.json().await.unwrap();
+            assert_eq!(page.tickets.len(), expected);
+        }
+
+        let res = client
+            .patch(
+                base_url
+                    .join(&format!("ticket/{}", create_result_data.id))
+                    .unwrap(),
+            )
+            .body(r#"{ "add_tags": ["Backend"] }"#)
+            .header("Content-Type", "application/json")
+            .send()
+            .await
+            .unwrap();
+        let data: data::Ticket = res.json().await.unwrap();
+        assert_eq!(data.tags.first().unwrap().as_str(), "backend");
+
+        for (tag_match, expected) in [("all", 0), ("any", 1)] {
+            let res = client
+                .get(base_url.clone())
+                .query(&[("tag", "backend"), ("tag", "bug"), ("tag_match", tag_match)])
+                .send()
+                .await
+                .unwrap();
+            let page: data::TicketPage = res.json().await.unwrap();
+            assert_eq!(page.tickets.len(), expected);
+        }
+
         let res = client
             .patch(
                 base_url
@@ -87,15 +204,887 @@ mod test {
             .await
             .unwrap();
 
+        assert_eq!(res.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
         let text_data = res.text().await.unwrap();
         assert_eq!(
             text_data,
-            r#"{"error":"Ticket title error: The title cannot be longer than 50 characters"}"#
+            r#"{"code":"invalid_title","error":"Ticket title error: The title cannot be longer than 50 characters, but it has 66"}"#
+        );
+
+        let res = client
+            .post(base_url.clone())
+            .body(r#"{ "title": "Test Title", "description": "  " }"#)
+            .header("Content-Type", "application/json")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+        let error: error::AppErrorWriter = res.json().await.unwrap();
+        assert_eq!(error.code, error::ErrorCode::InvalidDescription);
+
+        let ticket_url = base_url
+            .join(&format!("ticket/{}", create_result_data.id))
+            .unwrap();
+
+        let res = client.delete(ticket_url.clone()).send().await.unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::NO_CONTENT);
+
+        let res = client.get(ticket_url.clone()).send().await.unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::NOT_FOUND);
+        let text_data = res.text().await.unwrap();
+        assert_eq!(
+            text_data,
+            r#"{"code":"ticket_not_found","error":"Ticket not found"}"#
+        );
+
+        let res = client
+            .get(ticket_url.clone())
+            .query(&[("archived", "true")])
+            .send()
+            .await
+            .unwrap();
+        let data: data::Ticket = res.json().await.unwrap();
+        assert_eq!(data.id.0, create_result_data.id);
+
+        let res = client.delete(ticket_url.clone()).send().await.unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::CONFLICT);
+        let text_data = res.text().await.unwrap();
+        assert_eq!(
+            text_data,
+            r#"{"code":"ticket_archived","error":"Ticket is already archived"}"#
+        );
+
+        let res = client
+            .delete(ticket_url.clone())
+            .query(&[("hard", "true")])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::NO_CONTENT);
+
+        let res = client
+            .get(ticket_url)
+            .query(&[("archived", "true")])
+            .send()
+            .await
+            .unwrap();
+        let text_data = res.text().await.unwrap();
+        assert_eq!(
+            text_data,
+            r#"{"code":"ticket_not_found","error":"Ticket not found"}"#
+        );
+
+        server.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_servers_are_isolated() {
+        let first = spawn_server(store::TicketStore::new()).await;
+        let second = spawn_server(store::TicketStore::new()).await;
+        let (first_url, second_url) = (first.base_url.clone(), second.base_url.clone());
+        let client = reqwest::Client::new();
+
+        let res = client
+            .post(first_url.clone())
+            .body(r#"{ "title": "Only here", "description": "Test Description" }"#)
+            .header("Content-Type", "application/json")
+            .send()
+            .await
+            .unwrap();
+        let created: CreateResponse = res.json().await.unwrap();
+
+        let res = client
+            .get(first_url.join(&format!("ticket/{}", created.id)).unwrap())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+        let res = client
+            .get(second_url.join(&format!("ticket/{}", created.id)).unwrap())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::NOT_FOUND);
+
+        first.stop().await.unwrap();
+        second.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_patch_if_match() {
+        let server = spawn_server(store::TicketStore::new()).await;
+        let client = reqwest::Client::new();
+
+        let res = client
+            .post(server.base_url.clone())
+            .body(r#"{ "title": "Test Title", "description": "Test Description" }"#)
+            .header("Content-Type", "application/json")
+            .send()
+            .await
+            .unwrap();
+        let created: CreateResponse = res.json().await.unwrap();
+        let ticket_url = server
+            .base_url
+            .join(&format!("ticket/{}", created.id))
+            .unwrap();
+
+        let res = client.get(ticket_url.clone()).send().await.unwrap();
+        let etag = res.headers()["ETag"].clone();
+        assert_eq!(etag, r#""1""#);
+
+        let res = client
+            .patch(ticket_url.clone())
+            .body(r#"{ "status": "InProgress" }"#)
+            .header("Content-Type", "application/json")
+            .header("If-Match", etag.clone())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+        assert_eq!(res.headers()["ETag"], r#""2""#);
+
+        let res = client
+            .patch(ticket_url)
+            .body(r#"{ "status": "Done" }"#)
+            .header("Content-Type", "application/json")
+            .header("If-Match", etag)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::PRECONDITION_FAILED);
+        let error: error::AppErrorWriter = res.json().await.unwrap();
+        assert_eq!(error.code, error::ErrorCode::VersionMismatch);
+
+        let res = client
+            .get(
+                server
+                    .base_url
+                    .join(&format!("ticket/{}/history", created.id))
+                    .unwrap(),
+            )
+            .send()
+            .await
+            .unwrap();
+        let history: Vec<history::HistoryEntry> = res.json().await.unwrap();
+        let changes: Vec<_> = history.into_iter().map(|entry| entry.change).collect();
+        assert_eq!(
+            changes,
+            [
+                history::TicketChange::Created {
+                    title: "Test Title".try_into().unwrap(),
+                    description: "Test Description".try_into().unwrap(),
+                    assignee: None,
+                    tags: Default::default(),
+                    priority: None,
+                    due_date: None,
+                },
+                history::TicketChange::StatusChanged {
+                    from: data::Status::ToDo,
+                    to: data::Status::InProgress,
+                },
+            ]
+        );
+
+        server.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_comments() {
+        let server = spawn_server(store::TicketStore::new()).await;
+        let client = reqwest::Client::new();
+
+        let res = client
+            .post(server.base_url.clone())
+            .body(r#"{ "title": "Test Title", "description": "Test Description" }"#)
+            .header("Content-Type", "application/json")
+            .send()
+            .await
+            .unwrap();
+        let created: CreateResponse = res.json().await.unwrap();
+        let comments_url = server
+            .base_url
+            .join(&format!("ticket/{}/comments", created.id))
+            .unwrap();
+
+        for body in ["First", "Second"] {
+            let res = client
+                .post(comments_url.clone())
+                .json(&serde_json::json!({ "author": "Alice", "body": body }))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(res.status(), reqwest::StatusCode::CREATED);
+        }
+
+        let res = client
+            .post(comments_url.clone())
+            .body(r#"{ "author": "Alice", "body": " " }"#)
+            .header("Content-Type", "application/json")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+        let error: error::AppErrorWriter = res.json().await.unwrap();
+        assert_eq!(error.code, error::ErrorCode::InvalidComment);
+
+        let res = client
+            .get(comments_url.clone())
+            .query(&[("limit", "1")])
+            .send()
+            .await
+            .unwrap();
+        let page: comment::CommentPage = res.json().await.unwrap();
+        assert_eq!(page.comments.len(), 1);
+        assert_eq!(page.comments[0].body.as_str(), "First");
+        assert_eq!(page.comments[0].author, "Alice");
+
+        let res = client
+            .get(comments_url.clone())
+            .query(&[("cursor", page.next_cursor.unwrap().0.to_string())])
+            .send()
+            .await
+            .unwrap();
+        let page: comment::CommentPage = res.json().await.unwrap();
+        assert_eq!(page.comments.len(), 1);
+        assert_eq!(page.comments[0].body.as_str(), "Second");
+
+        let ticket_url = server
+            .base_url
+            .join(&format!("ticket/{}", created.id))
+            .unwrap();
+        let res = client
+            .delete(ticket_url)
+            .query(&[("hard", "true")])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::NO_CONTENT);
+
+        let res = client.get(comments_url).send().await.unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::NOT_FOUND);
+
+        server.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_links() {
+        let server = spawn_server(store::TicketStore::new()).await;
+        let client = reqwest::Client::new();
+
+        let mut ids = Vec::new();
+        for title in ["Blocker", "Blocked"] {
+            let res = client
+                .post(server.base_url.clone())
+                .json(&serde_json::json!({ "title": title, "description": "Test Description" }))
+                .send()
+                .await
+                .unwrap();
+            let created: CreateResponse = res.json().await.unwrap();
+            ids.push(created.id);
+        }
+        let (blocker, blocked) = (ids[0], ids[1]);
+        let ticket_url =
+            |id: u64, path: &str| server.base_url.join(&format!("ticket/{id}{path}")).unwrap();
+
+        let res = client
+            .post(ticket_url(blocker, "/links"))
+            .json(&serde_json::json!({ "kind": "blocks", "target": blocked }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::CREATED);
+
+        let res = client
+            .post(ticket_url(blocked, "/links"))
+            .json(&serde_json::json!({ "kind": "blocks", "target": blocker }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+        let error: error::AppErrorWriter = res.json().await.unwrap();
+        assert_eq!(error.code, error::ErrorCode::InvalidLink);
+
+        let res = client
+            .get(ticket_url(blocked, "/links"))
+            .send()
+            .await
+            .unwrap();
+        let links: Vec<link::Link> = res.json().await.unwrap();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].source.0, blocker);
+
+        for status in ["InProgress", "Done"] {
+            let res = client
+                .patch(ticket_url(blocked, ""))
+                .json(&serde_json::json!({ "status": status }))
+                .send()
+                .await
+                .unwrap();
+            if status == "Done" {
+                assert_eq!(res.status(), reqwest::StatusCode::CONFLICT);
+                let error: error::AppErrorWriter = res.json().await.unwrap();
+                assert_eq!(error.code, error::ErrorCode::TicketBlocked);
+            }
+        }
+
+        let res = client
+            .delete(ticket_url(blocker, &format!("/links/blocks/{blocked}")))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::NO_CONTENT);
+
+        let res = client
+            .patch(ticket_url(blocked, ""))
+            .json(&serde_json::json!({ "status": "Done" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+        server.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let server = spawn_server(store::TicketStore::new()).await;
+        let client = reqwest::Client::new();
+
+        for (title, description) in [
+            ("Fix login", "The login page crashes"),
+            ("Write docs", "Document the login flow"),
+            ("Fix signup", "Unrelated"),
+        ] {
+            client
+                .post(server.base_url.clone())
+                .json(&serde_json::json!({ "title": title, "description": description }))
+                .send()
+                .await
+                .unwrap();
+        }
+
+        let search_url = server.base_url.join("ticket/search").unwrap();
+        let res = client
+            .get(search_url.clone())
+            .query(&[("q", "Login"), ("limit", "5")])
+            .send()
+            .await
+            .unwrap();
+        let hits: Vec<data::SearchHit> = res.json().await.unwrap();
+        let titles: Vec<_> = hits.iter().map(|hit| hit.ticket.title.as_str()).collect();
+        assert_eq!(titles, ["Fix login", "Write docs"]);
+        assert!(hits[0].score > hits[1].score);
+
+        let res = client.get(search_url).send().await.unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        server.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_events() {
+        let server = spawn_server(store::TicketStore::new()).await;
+        let client = reqwest::Client::new();
+
+        // A bad status is a malformed query parameter, like any other.
+        let events_url = server.base_url.join("ticket/events").unwrap();
+        for url in [events_url.clone(), server.base_url.clone()] {
+            let res = client
+                .get(url)
+                .query(&[("status", "nope")])
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+            let error: error::AppErrorWriter = res.json().await.unwrap();
+            assert_eq!(error.code, error::ErrorCode::InvalidQueryParameter);
+        }
+
+        // The subscription is made before the response headers are sent.
+        let mut events = client
+            .get(events_url)
+            .query(&[("status", "inprogress")])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(events.status(), reqwest::StatusCode::OK);
+        assert_eq!(events.headers()["content-type"], "text/event-stream");
+
+        for title in ["First", "Second"] {
+            client
+                .post(server.base_url.clone())
+                .json(&serde_json::json!({ "title": title, "description": "A description" }))
+                .send()
+                .await
+                .unwrap();
+        }
+        client
+            .patch(server.base_url.join("ticket/1").unwrap())
+            .json(&serde_json::json!({ "status": "InProgress" }))
+            .send()
+            .await
+            .unwrap();
+
+        // Creations are filtered out, since new tickets are still to do.
+        let mut text = String::new();
+        while !text.contains("\n\n") {
+            let chunk = events.chunk().await.unwrap().unwrap();
+            text.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+        let (event, _) = text.split_once("\n\n").unwrap();
+        let mut lines = event.lines();
+        assert_eq!(lines.next(), Some("event:patched"));
+        let data = lines.next().unwrap().strip_prefix("data:").unwrap();
+        let event: feed::TicketEvent = serde_json::from_str(data).unwrap();
+        assert!(matches!(event, feed::TicketEvent::Patched { .. }));
+        assert_eq!(event.ticket().id, store::TicketId(1));
+        assert_eq!(event.ticket().status, data::Status::InProgress);
+
+        // Open streams end with the server, well before the shutdown times out.
+        let stopped = tokio::time::timeout(std::time::Duration::from_secs(5), server.stop());
+        stopped.await.unwrap().unwrap();
+        while let Ok(Some(_)) = events.chunk().await {}
+    }
+
+    #[tokio::test]
+    async fn test_bulk() {
+        let server = spawn_server(store::TicketStore::new()).await;
+        let client = reqwest::Client::new();
+        let bulk_url = server.base_url.join("ticket/bulk").unwrap();
+        let drafts = serde_json::json!([
+            { "title": "First", "description": "Test Description" },
+            { "title": "", "description": "Test Description" },
+        ]);
+
+        let res = client
+            .post(bulk_url.clone())
+            .query(&[("atomic", "true")])
+            .json(&drafts)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+        let items: Vec<bulk::BulkItem> = res.json().await.unwrap();
+        let codes: Vec<_> = items
+            .iter()
+            .map(|item| item.error.as_ref().unwrap().code)
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                error::ErrorCode::BatchRejected,
+                error::ErrorCode::InvalidTitle
+            ]
+        );
+
+        let res = client
+            .post(bulk_url.clone())
+            .json(&drafts)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+        let items: Vec<bulk::BulkItem> = res.json().await.unwrap();
+        // The rejected batch didn't use up any ids.
+        assert_eq!(items[0].id, Some(store::TicketId(0)));
+        assert!(items[1].error.is_some());
+
+        let res = client
+            .patch(bulk_url)
+            .json(&serde_json::json!([
+                { "id": 0, "patch": { "status": "InProgress" }, "version": 1 },
+                { "id": 7, "patch": { "status": "InProgress" } },
+            ]))
+            .send()
+            .await
+            .unwrap();
+        let items: Vec<bulk::BulkItem> = res.json().await.unwrap();
+        assert_eq!(items[0].version, Some(2));
+        assert_eq!(items[1].id, Some(store::TicketId(7)));
+        assert_eq!(
+            items[1].error.as_ref().unwrap().code,
+            error::ErrorCode::TicketNotFound
         );
 
-        server_handle.abort();
-        let result = server_handle.await;
-        println!("{:?}", result);
-        assert!(result.is_err());
+        server.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_keeps_tickets() {
+        let dir = tempfile::tempdir().unwrap();
+        let open_store =
+            || store::TicketStore::open(storage::LogStorage::open(dir.path()).unwrap()).unwrap();
+        let client = reqwest::Client::new();
+
+        let server = spawn_server(open_store()).await;
+        let res = client
+            .post(server.base_url.clone())
+            .body(r#"{ "title": "Survivor", "description": "Test Description" }"#)
+            .header("Content-Type", "application/json")
+            .send()
+            .await
+            .unwrap();
+        let created: CreateResponse = res.json().await.unwrap();
+        server.stop().await.unwrap();
+
+        let server = spawn_server(open_store()).await;
+        let res = client
+            .get(
+                server
+                    .base_url
+                    .join(&format!("ticket/{}", created.id))
+                    .unwrap(),
+            )
+            .send()
+            .await
+            .unwrap();
+        let data: data::Ticket = res.json().await.unwrap();
+        assert_eq!(data.title, "Survivor");
+        server.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_config_applies_to_store() {
+        let mut policy = ticket_fields::ValidationPolicy::DEFAULT;
+        policy.description.max_length = 10;
+        let config = config::ServerConfig {
+            validation: Some(policy),
+            ..Default::default()
+        };
+        let server = spawn_server_with_config(store::TicketStore::new(), config).await;
+        let client = reqwest::Client::new();
+
+        let res = client
+            .post(server.base_url.clone())
+            .body(r#"{ "title": "Test Title", "description": "Test Description" }"#)
+            .header("Content-Type", "application/json")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+        let error: error::AppErrorWriter = res.json().await.unwrap();
+        assert_eq!(error.code, error::ErrorCode::InvalidDescription);
+
+        server.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unset_config_keeps_store_settings() {
+        let store =
+            store::TicketStore::new().with_workflow(workflow::StatusWorkflow::unrestricted());
+        let server = spawn_server(store).await;
+        let client = reqwest::Client::new();
+
+        let res = client
+            .post(server.base_url.clone())
+            .body(r#"{ "title": "Test Title", "description": "Test Description" }"#)
+            .header("Content-Type", "application/json")
+            .send()
+            .await
+            .unwrap();
+        let created: CreateResponse = res.json().await.unwrap();
+
+        // ToDo -> Done skips InProgress, which only the default workflow requires.
+        let res = client
+            .patch(
+                server
+                    .base_url
+                    .join(&format!("ticket/{}", created.id))
+                    .unwrap(),
+            )
+            .json(&serde_json::json!({ "status": "Done" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+        server.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_client() {
+        use client::{ClientConfig, TicketClient};
+        use error::{ClientError, ErrorCode};
+        use std::time::Duration;
+
+        let server = spawn_server(store::TicketStore::new()).await;
+        let config = ClientConfig::new(server.base_url.join("/").unwrap())
+            .with_timeout(Duration::from_secs(5));
+        let client = TicketClient::new(config).unwrap();
+
+        let err = TicketClient::new(ClientConfig::new("mailto:x".parse().unwrap())).unwrap_err();
+        assert!(matches!(err, ClientError::InvalidBaseUrl(_)));
+
+        let draft = data::TicketDraft::new(
+            "Test Title".try_into().unwrap(),
+            "Test Description".try_into().unwrap(),
+        );
+        let id = client.create(&draft).await.unwrap();
+        let ticket = client.get(id).await.unwrap();
+        assert_eq!(ticket.title, "Test Title");
+
+        let patch = data::TicketPatch {
+            assignee: Some(Some("Alice".try_into().unwrap())),
+            ..Default::default()
+        };
+        let ticket = client.patch(id, &patch, Some(1)).await.unwrap();
+        assert_eq!(ticket.assignee.unwrap(), "Alice");
+        assert_eq!(ticket.version, 2);
+
+        let error = client.patch(id, &patch, Some(1)).await.unwrap_err();
+        assert_eq!(error.code(), Some(ErrorCode::VersionMismatch));
+        let error = client.get(store::TicketId(42)).await.unwrap_err();
+        assert!(matches!(
+            error,
+            ClientError::Api { status, .. } if status == reqwest::StatusCode::NOT_FOUND
+        ));
+
+        server.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_openapi() {
+        let server = spawn_server(store::TicketStore::new()).await;
+
+        let res = reqwest::get(server.base_url.join("/api/openapi.json").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+        let served: serde_json::Value = res.json().await.unwrap();
+        assert_eq!(served, serde_json::to_value(server::openapi()).unwrap());
+
+        // Run with `UPDATE_OPENAPI=1` to accept changes to the API.
+        let spec = server::openapi().to_pretty_json().unwrap() + "\n";
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("openapi.json");
+        if std::env::var_os("UPDATE_OPENAPI").is_some() {
+            std::fs::write(&path, &spec).unwrap();
+        }
+        let snapshot = std::fs::read_to_string(&path).unwrap();
+        assert!(
+            spec == snapshot,
+            "the OpenAPI document no longer matches openapi.json, \
+             rerun with UPDATE_OPENAPI=1 if the change is intended"
+        );
+
+        server.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_auth() {
+        use auth::{AuthConfig, Caller, Role};
+        use client::{ClientConfig, TicketClient};
+        use error::ErrorCode;
+
+        let mut auth = AuthConfig::default();
+        for (token, user, role) in [
+            ("reader-token", "Rita", Role::Reader),
+            ("alice-token", "Alice", Role::Editor),
+            ("bob-token", "Bob", Role::Editor),
+            ("admin-token", "Ada", Role::Admin),
+        ] {
+            let user = user.try_into().unwrap();
+            auth.tokens.insert(token.into(), Caller { user, role });
+        }
+        let config = config::ServerConfig {
+            auth,
+            ..Default::default()
+        };
+        let server = spawn_server_with_config(store::TicketStore::new(), config).await;
+        let client = |token: Option<&str>| {
+            let config = ClientConfig::new(server.base_url.join("/").unwrap());
+            let config = match token {
+                Some(token) => config.with_token(token),
+                None => config,
+            };
+            TicketClient::new(config).unwrap()
+        };
+
+        let draft = data::TicketDraft::new(
+            "Test Title".try_into().unwrap(),
+            "Test Description".try_into().unwrap(),
+        );
+        for (token, code) in [
+            (None, ErrorCode::Unauthorized),
+            (Some("wrong-token"), ErrorCode::Unauthorized),
+            (Some("reader-token"), ErrorCode::Forbidden),
+        ] {
+            let error = client(token).create(&draft).await.unwrap_err();
+            assert_eq!(error.code(), Some(code), "{token:?}");
+        }
+
+        let id = client(Some("alice-token")).create(&draft).await.unwrap();
+        let ticket = client(Some("reader-token")).get(id).await.unwrap();
+        assert_eq!(ticket.created_by.unwrap(), "Alice");
+
+        let patch = data::TicketPatch::new(None, None, Some(data::Status::InProgress)).unwrap();
+        let error = client(Some("bob-token"))
+            .patch(id, &patch, None)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), Some(ErrorCode::Forbidden));
+        client(Some("alice-token"))
+            .patch(id, &patch, None)
+            .await
+            .unwrap();
+        let patch = data::TicketPatch::new(None, None, Some(data::Status::Done)).unwrap();
+        client(Some("admin-token"))
+            .patch(id, &patch, None)
+            .await
+            .unwrap();
+
+        // Bob can only link his own tickets.
+        let bobs = client(Some("bob-token")).create(&draft).await.unwrap().0;
+        let id = id.0;
+        let link = |token: &str| {
+            reqwest::Client::new()
+                .post(
+                    server
+                        .base_url
+                        .join(&format!("ticket/{bobs}/links"))
+                        .unwrap(),
+                )
+                .bearer_auth(token)
+                .body(format!(r#"{{ "kind": "blocks", "target": {id} }}"#))
+                .header("Content-Type", "application/json")
+                .send()
+        };
+        let res = link("bob-token").await.unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::FORBIDDEN);
+        let res = link("admin-token").await.unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::CREATED);
+        let res = reqwest::Client::new()
+            .delete(
+                server
+                    .base_url
+                    .join(&format!("ticket/{bobs}/links/blocks/{id}"))
+                    .unwrap(),
+            )
+            .bearer_auth("bob-token")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::FORBIDDEN);
+
+        // The OpenAPI document stays public.
+        let res = reqwest::get(server.base_url.join("/api/openapi.json").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+        server.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_limits() {
+        use chrono::{DateTime, Duration};
+        use clock::FixedClock;
+        use limit::{LimitsConfig, RateLimit};
+
+        let clock = Arc::new(FixedClock::new(DateTime::UNIX_EPOCH));
+        let config = config::ServerConfig {
+            limits: LimitsConfig {
+                max_body_bytes: 256,
+                max_bulk_body_bytes: 1024,
+                rate: Some(RateLimit {
+                    burst: 2,
+                    per_second: 1.0,
+                }),
+            },
+            ..Default::default()
+        };
+        let store = store::TicketStore::new().with_clock(clock.clone());
+        let server = spawn_server_with_config(store, config).await;
+        let client = reqwest::Client::new();
+
+        for _ in 0..2 {
+            let res = client.get(server.base_url.clone()).send().await.unwrap();
+            assert_eq!(res.status(), reqwest::StatusCode::OK);
+        }
+        let res = client.get(server.base_url.clone()).send().await.unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers()["retry-after"], "1");
+
+        clock.advance(Duration::seconds(1));
+        let res = client.get(server.base_url.clone()).send().await.unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+        clock.advance(Duration::seconds(10));
+        let description = "a".repeat(400);
+        let res = client
+            .post(server.base_url.clone())
+            .json(&serde_json::json!({ "title": "Big", "description": description }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::PAYLOAD_TOO_LARGE);
+        let error: error::AppErrorWriter = res.json().await.unwrap();
+        assert_eq!(error.code, error::ErrorCode::PayloadTooLarge);
+
+        let res = client
+            .post(server.base_url.clone())
+            .json(&serde_json::json!({ "title": "Small", "description": "Fits" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+        // Bulk requests get a limit of their own.
+        let bulk_url = server.base_url.join("ticket/bulk").unwrap();
+        let draft = serde_json::json!({ "title": "Bulk", "description": "Fits" });
+        for (drafts, status) in [
+            (8, reqwest::StatusCode::OK),
+            (32, reqwest::StatusCode::PAYLOAD_TOO_LARGE),
+        ] {
+            clock.advance(Duration::seconds(10));
+            let res = client
+                .post(bulk_url.clone())
+                .json(&vec![&draft; drafts])
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(res.status(), status);
+        }
+
+        server.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_token_guessing_is_throttled() {
+        use auth::{Caller, Role};
+        use limit::{LimitsConfig, RateLimit};
+
+        let mut config = config::ServerConfig {
+            limits: LimitsConfig {
+                rate: Some(RateLimit {
+                    burst: 2,
+                    per_second: 0.01,
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let caller = Caller {
+            user: "Alice".try_into().unwrap(),
+            role: Role::Reader,
+        };
+        config.auth.tokens.insert("secret".into(), caller);
+        let server = spawn_server_with_config(store::TicketStore::new(), config).await;
+        let client = reqwest::Client::new();
+
+        let guess = |token: &str| {
+            client
+                .get(server.base_url.clone())
+                .bearer_auth(token)
+                .send()
+        };
+        for _ in 0..2 {
+            let res = guess("guess").await.unwrap();
+            assert_eq!(res.status(), reqwest::StatusCode::UNAUTHORIZED);
+        }
+        let res = guess("secret").await.unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+
+        server.stop().await.unwrap();
     }
 }
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::clock::Clock;
+use crate::error::{AppError, AppResult};
+
+// Each client may send `burst` requests at once, then `per_second` on average.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct RateLimit {
+    pub burst: u32,
+    pub per_second: f64,
+}
+
+impl RateLimit {
+    // Whether clients can send any request at all.
+    pub fn is_valid(&self) -> bool {
+        self.burst > 0 && self.per_second > 0.0 && self.per_second.is_finite()
+    }
+}
+
+// The `limits` section of the server config.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LimitsConfig {
+    pub max_body_bytes: usize,
+    // Bulk requests carry many tickets at once, so they get a limit of their own.
+    pub max_bulk_body_bytes: usize,
+    // No rate limiting unless configured.
+    pub rate: Option<RateLimit>,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: 64 * 1024,
+            max_bulk_body_bytes: 4 * 1024 * 1024,
+            rate: None,
+        }
+    }
+}
+
+// Buckets that have been refilled are dropped this often,
+// so that a stream of new clients can't grow the map forever.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+// A token bucket per client.
+pub struct RateLimiter {
+    limit: RateLimit,
+    clock: Arc<dyn Clock>,
+    buckets: Mutex<Buckets>,
+}
+
+struct Buckets {
+    by_client: HashMap<String, Bucket>,
+    pruned: DateTime<Utc>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: DateTime<Utc>,
+}
+
+impl Bucket {
+    fn refill(&mut self, limit: &RateLimit, now: DateTime<Utc>) {
+        // A clock going backwards adds nothing.
+        let elapsed = (now - self.updated).to_std().unwrap_or_default();
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() * limit.per_second).min(f64::from(limit.burst));
+        self.updated = self.updated.max(now);
+    }
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit, clock: Arc<dyn Clock>) -> Self {
+        let buckets = Buckets {
+            by_client: HashMap::new(),
+            pruned: clock.now(),
+        };
+        Self {
+            limit,
+            clock,
+            buckets: Mutex::new(buckets),
+        }
+    }
+
+    // Takes a token from the bucket of `client`, or tells how many seconds
+    // to wait for the next one.
+    pub fn check(&self, client: &str) -> AppResult<()> {
+        let now = self.clock.now();
+        let mut buckets = self.buckets.lock().map_err(|_| AppError::PoisonError)?;
+
+        if (now - buckets.pruned).to_std().unwrap_or_default() >= PRUNE_INTERVAL {
+            buckets.by_client.retain(|_, bucket| {
+                bucket.refill(&self.limit, now);
+                bucket.tokens < f64::from(self.limit.burst)
+            });
+            buckets.pruned = now;
+        }
+
+        let bucket = buckets
+            .by_client
+            .entry(client.to_owned())
+            .or_insert(Bucket {
+                tokens: f64::from(self.limit.burst),
+                updated: now,
+            });
+        bucket.refill(&self.limit, now);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        let retry_after = ((1.0 - bucket.tokens) / self.limit.per_second).ceil();
+        Err(AppError::TooManyRequests {
+            retry_after: retry_after as u64,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+    use chrono::Duration;
+
+    #[test]
+    fn test_token_bucket() {
+        let clock = Arc::new(FixedClock::new(DateTime::UNIX_EPOCH));
+        let limit = RateLimit {
+            burst: 2,
+            per_second: 0.5,
+        };
+        let limiter = RateLimiter::new(limit, clock.clone());
+
+        limiter.check("alice").unwrap();
+        limiter.check("alice").unwrap();
+        assert!(matches!(
+            limiter.check("alice"),
+            Err(AppError::TooManyRequests { retry_after: 2 })
+        ));
+        // Clients don't share buckets.
+        limiter.check("bob").unwrap();
+
+        clock.advance(Duration::seconds(1));
+        assert!(matches!(
+            limiter.check("alice"),
+            Err(AppError::TooManyRequests { retry_after: 1 })
+        ));
+        clock.advance(Duration::seconds(1));
+        limiter.check("alice").unwrap();
+
+        // The bucket never holds more than `burst` tokens.
+        clock.advance(Duration::hours(1));
+        limiter.check("alice").unwrap();
+        limiter.check("alice").unwrap();
+        assert!(limiter.check("alice").is_err());
+    }
+
+    #[test]
+    fn test_refilled_buckets_are_pruned() {
+        let clock = Arc::new(FixedClock::new(DateTime::UNIX_EPOCH));
+        let limit = RateLimit {
+            burst: 2,
+            per_second: 1.0,
+        };
+        let limiter = RateLimiter::new(limit, clock.clone());
+        let clients = || limiter.buckets.lock().unwrap().by_client.len();
+
+        limiter.check("alice").unwrap();
+        limiter.check("bob").unwrap();
+        limiter.check("bob").unwrap();
+        assert_eq!(clients(), 2);
+
+        // Pruning waits for the interval, however many clients there are.
+        clock.advance(Duration::seconds(30));
+        limiter.check("carol").unwrap();
+        assert_eq!(clients(), 3);
+
+        // By then every bucket is full again, and only the one Carol just took from is kept.
+        clock.advance(Duration::seconds(30));
+        limiter.check("carol").unwrap();
+        assert_eq!(clients(), 1);
+    }
+
+    #[test]
+    fn test_rate_limit_is_valid() {
+        let limit = |burst, per_second| RateLimit { burst, per_second };
+        assert!(limit(1, 0.5).is_valid());
+        assert!(!limit(0, 1.0).is_valid());
+        assert!(!limit(1, 0.0).is_valid());
+        assert!(!limit(1, -1.0).is_valid());
+        assert!(!limit(1, f64::NAN).is_valid());
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::BTreeSet;
+
+use salvo::oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::TicketLinkError;
+use crate::store::TicketId;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[salvo(schema(symbol = "LinkKind"))]
+pub enum LinkKind {
+    // The source is the parent of the target, its subtask.
+    Parent,
+    // The target cannot be done before the source is.
+    Blocks,
+    // The source duplicates the target.
+    DuplicateOf,
+}
+
+impl LinkKind {
+    // Whether links of this kind have to form a directed acyclic graph.
+    pub fn is_acyclic(&self) -> bool {
+        matches!(self, Self::Parent | Self::Blocks)
+    }
+}
+
+impl TryFrom<&str> for LinkKind {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "parent" => Ok(Self::Parent),
+            "blocks" => Ok(Self::Blocks),
+            "duplicate_of" => Ok(Self::DuplicateOf),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, ToSchema)]
+#[salvo(schema(symbol = "Link"))]
+pub struct Link {
+    pub source: TicketId,
+    pub kind: LinkKind,
+    pub target: TicketId,
+}
+
+// A link as posted to its source ticket.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[salvo(schema(symbol = "LinkDraft"))]
+pub struct LinkDraft {
+    pub kind: LinkKind,
+    pub target: TicketId,
+}
+
+impl LinkDraft {
+    pub fn from_source(self, source: TicketId) -> Link {
+        Link {
+            source,
+            kind: self.kind,
+            target: self.target,
+        }
+    }
+}
+
+// Every link between tickets. Links are directed:
+// the child and blocked-by views are the same links seen from the target.
+#[derive(Debug, Default)]
+pub struct TicketLinks {
+    links: BTreeSet<Link>,
+}
+
+impl TicketLinks {
+    // Checks that `link` can be added without breaking the rules on links.
+    pub fn check(&self, link: &Link) -> Result<(), TicketLinkError> {
+        if link.source == link.target {
+            return Err(TicketLinkError::SelfLink);
+        }
+        if self.links.contains(link) {
+            return Err(TicketLinkError::AlreadyLinked);
+        }
+        if link.kind == LinkKind::Parent {
+            if let Some(parent) = self.sources(link.target, LinkKind::Parent).next() {
+                return Err(TicketLinkError::AlreadyHasParent(parent));
+            }
+        }
+        if link.kind.is_acyclic() && self.reaches(link.target, link.source, link.kind) {
+            return Err(TicketLinkError::Cycle);
+        }
+
+        Ok(())
+    }
+
+    pub fn insert(&mut self, link: Link) {
+        self.links.insert(link);
+    }
+
+    pub fn remove(&mut self, link: &Link) -> bool {
+        self.links.remove(link)
+    }
+
+    // Drops every link from or to `id`.
+    pub fn remove_ticket(&mut self, id: TicketId) {
+        self.links
+            .retain(|link| link.source != id && link.target != id);
+    }
+
+    // The links from or to `id`.
+    pub fn of(&self, id: TicketId) -> Vec<Link> {
+        self.links
+            .iter()
+            .filter(|link| link.source == id || link.target == id)
+            .copied()
+            .collect()
+    }
+
+    // The tickets linked to `id` by a link of `kind` pointing at it,
+    // e.g. its blockers for `LinkKind::Blocks`.
+    pub fn sources(&self, id: TicketId, kind: LinkKind) -> impl Iterator<Item = TicketId> + '_ {
+        self.links
+            .iter()
+            .filter(move |link| link.kind == kind && link.target == id)
+            .map(|link| link.source)
+    }
+
+    fn targets(&self, id: TicketId, kind: LinkKind) -> impl Iterator<Item = TicketId> + '_ {
+        self.links
+            .iter()
+            .filter(move |link| link.kind == kind && link.source == id)
+            .map(|link| link.target)
+    }
+
+    // Whether `to` can be reached from `from` following links of `kind`.
+    fn reaches(&self, from: TicketId, to: TicketId, kind: LinkKind) -> bool {
+        let mut seen = BTreeSet::new();
+        let mut pending = vec![from];
+        while let Some(id) = pending.pop() {
+            if id == to {
+                return true;
+            }
+            if seen.insert(id) {
+                pending.extend(self.targets(id, kind));
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(source: u64, kind: LinkKind, target: u64) -> Link {
+        Link {
+            source: TicketId(source),
+            kind,
+            target: TicketId(target),
+        }
+    }
+
+    #[test]
+    fn test_cycles_are_rejected() {
+        let mut links = TicketLinks::default();
+        links.insert(link(0, LinkKind::Blocks, 1));
+        links.insert(link(1, LinkKind::Blocks, 2));
+
+        assert!(matches!(
+            links.check(&link(2, LinkKind::Blocks, 0)),
+            Err(TicketLinkError::Cycle)
+        ));
+        assert!(links.check(&link(0, LinkKind::Blocks, 2)).is_ok());
+        // Cycles are only checked among links of the same kind.
+        assert!(links.check(&link(2, LinkKind::Parent, 0)).is_ok());
+        assert!(links.check(&link(2, LinkKind::DuplicateOf, 0)).is_ok());
+    }
+
+    #[test]
+    fn test_link_rules() {
+        let mut links = TicketLinks::default();
+        links.insert(link(0, LinkKind::Parent, 1));
+
+        assert!(matches!(
+            links.check(&link(1, LinkKind::Blocks, 1)),
+            Err(TicketLinkError::SelfLink)
+        ));
+        assert!(matches!(
+            links.check(&link(0, LinkKind::Parent, 1)),
+            Err(TicketLinkError::AlreadyLinked)
+        ));
+        assert!(matches!(
+            links.check(&link(2, LinkKind::Parent, 1)),
+            Err(TicketLinkError::AlreadyHasParent(TicketId(0)))
+        ));
+
+        links.insert(link(2, LinkKind::Blocks, 0));
+        assert_eq!(links.of(TicketId(0)).len(), 2);
+        links.remove_ticket(TicketId(0));
+        assert!(links.of(TicketId(2)).is_empty());
+    }
+}
@@ -1,109 +1,1123 @@
-use std::sync::OnceLock;
+use std::convert::Infallible;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
 
 use crate::{
-    data::{validate_ticket_draft, validate_ticket_patch, TicketPatch},
+    auth::{AuthConfig, Caller, Role},
+    bulk::{BulkItem, BulkPatch},
+    clock::Clock,
+    comment::{Comment, CommentDraft, CommentId, CommentPage, CommentQuery},
+    config::ServerConfig,
+    data::{
+        SearchHit, SearchQuery, Status, TagMatch, Ticket, TicketAssignee, TicketDraft, TicketPage,
+        TicketPatch, TicketQuery, TicketSort, TicketTag,
+    },
     error::{AppError, AppResult, ServerError},
+    feed::{EventFilter, TicketEvent},
+    history::HistoryEntry,
+    limit::{LimitsConfig, RateLimiter},
+    link::{Link, LinkDraft, LinkKind},
     store,
 };
 
+use futures_util::{stream, Stream, StreamExt};
+use http_body_util::LengthLimitError;
+use salvo::conn::tcp::TcpAcceptor;
+use salvo::http::header::{HeaderValue, AUTHORIZATION, ETAG, IF_MATCH};
+use salvo::http::{mime, ParseError};
+use salvo::oapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use salvo::oapi::SecurityRequirement;
 use salvo::prelude::*;
-use serde_json::json;
+use salvo::sse::{SseEvent, SseKeepAlive};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use ticket_fields::FieldError;
+use tokio::sync::broadcast::{self, error::RecvError};
 use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
 
-pub static TICKET_STORE: OnceLock<RwLock<store::TicketStore>> = OnceLock::new();
+// Each server gets its own store, injected into the `Depot` of every request.
+pub type SharedStore = Arc<RwLock<store::TicketStore>>;
 
+fn shared_store(depot: &Depot) -> AppResult<&SharedStore> {
+    depot
+        .obtain::<SharedStore>()
+        .map_err(|_| AppError::TicketStoreNotInitialized)
+}
+
+// Puts the `Caller` in the `Depot`, when authentication is enabled.
+#[handler]
+async fn authenticate(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+    ctrl: &mut FlowCtrl,
+) {
+    let Ok(auth) = depot.obtain::<AuthConfig>() else {
+        return;
+    };
+    if !auth.is_enabled() {
+        return;
+    }
+
+    let authorization = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|authorization| authorization.to_str().ok());
+    match auth.authenticate(authorization) {
+        Ok(caller) => {
+            let caller = caller.clone();
+            depot.inject(caller);
+        }
+        Err(e) => {
+            e.write(req, depot, res).await;
+            ctrl.skip_rest();
+        }
+    }
+}
+
+// Throttles each IP address. It runs before authentication, so that guessing
+// tokens is throttled like any other request.
+#[handler]
+async fn throttle_address(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+    ctrl: &mut FlowCtrl,
+) {
+    let client = client_ip(req);
+    throttle(&client, req, depot, res, ctrl).await;
+}
+
+// Throttles each authenticated user too, wherever they connect from.
+#[handler]
+async fn throttle_user(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+    ctrl: &mut FlowCtrl,
+) {
+    let Ok(caller) = depot.obtain::<Caller>() else {
+        return;
+    };
+    let client = format!("user:{}", caller.user.as_str());
+    throttle(&client, req, depot, res, ctrl).await;
+}
+
+async fn throttle(
+    client: &str,
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+    ctrl: &mut FlowCtrl,
+) {
+    let Ok(limiter) = depot.obtain::<Arc<RateLimiter>>() else {
+        return;
+    };
+
+    if let Err(e) = limiter.check(client) {
+        e.write(req, depot, res).await;
+        ctrl.skip_rest();
+    }
+}
+
+fn client_ip(req: &Request) -> String {
+    let addr = req.remote_addr();
+    let ip = addr
+        .as_ipv4()
+        .map(|addr| addr.ip().to_string())
+        .or_else(|| addr.as_ipv6().map(|addr| addr.ip().to_string()));
+    format!("ip:{}", ip.unwrap_or_default())
+}
+
+// Reads the body up to the configured size, so that handlers parse it from memory.
 #[handler]
-pub async fn get(res: &mut Response, req: &mut Request) -> AppResult<()> {
+async fn limit_body_size(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+    ctrl: &mut FlowCtrl,
+) {
+    let Ok(limits) = depot.obtain::<LimitsConfig>() else {
+        return;
+    };
+    let max = limits.max_body_bytes;
+    read_body(max, req, depot, res, ctrl).await;
+}
+
+// Like `limit_body_size`, for the bulk routes.
+#[handler]
+async fn limit_bulk_body_size(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+    ctrl: &mut FlowCtrl,
+) {
+    let Ok(limits) = depot.obtain::<LimitsConfig>() else {
+        return;
+    };
+    let max = limits.max_bulk_body_bytes;
+    read_body(max, req, depot, res, ctrl).await;
+}
+
+async fn read_body(
+    max: usize,
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+    ctrl: &mut FlowCtrl,
+) {
+    if let Err(e) = req.payload_with_max_size(max).await {
+        let e = match e {
+            ParseError::Other(e) if e.downcast_ref::<LengthLimitError>().is_some() => {
+                AppError::PayloadTooLarge { max }
+            }
+            e => e.into(),
+        };
+        e.write(req, depot, res).await;
+        ctrl.skip_rest();
+    }
+}
+
+// Without authentication there is no caller, and everything is allowed.
+fn authorize(depot: &Depot, role: Role) -> AppResult<Option<&Caller>> {
+    let Ok(caller) = depot.obtain::<Caller>() else {
+        return Ok(None);
+    };
+    caller.require(role)?;
+    Ok(Some(caller))
+}
+
+// Tickets that don't exist are left for the store to report.
+async fn check_may_change(
+    store: &store::TicketStore,
+    caller: Option<&Caller>,
+    id: store::TicketId,
+) -> AppResult<()> {
+    let Some(caller) = caller else {
+        return Ok(());
+    };
+    let Some(ticket) = store.get(id).await else {
+        return Ok(());
+    };
+    if !caller.may_change(&ticket) {
+        return Err(AppError::Forbidden);
+    }
+    Ok(())
+}
+
+// A link shows up on, and may hold back, both of its tickets.
+async fn check_may_change_link(
+    store: &store::TicketStore,
+    caller: Option<&Caller>,
+    link: &Link,
+) -> AppResult<()> {
+    check_may_change(store, caller, link.source).await?;
+    check_may_change(store, caller, link.target).await
+}
+
+#[endpoint(
+    parameters(
+        ("id" = u64, Path, description = "The ticket id"),
+        ("archived" = Option<bool>, Query, description = "Also look at archived tickets"),
+    ),
+    responses(
+        (status_code = 200, description = "The ticket", body = Ticket),
+    ),
+    status_codes(200, 400, 401, 404, 429, 500),
+)]
+pub async fn get(res: &mut Response, req: &mut Request, depot: &mut Depot) -> AppResult<()> {
     let id = req
         .param::<u64>("id")
         .ok_or_else(|| AppError::InvalidTicketId)?;
 
-    let store = TICKET_STORE
-        .get()
-        .ok_or_else(|| AppError::TicketStoreNotInitialized)?;
+    let store = shared_store(depot)?;
 
-    let ticket = store
-        .read()
-        .await
-        .get(store::TicketId(id))
-        .ok_or_else(|| AppError::NotTicket)?;
+    let include_archived = parse_flag(req, "archived")?;
 
-    let data = ticket.read().await.to_owned();
+    let data = {
+        let store = store.read().await;
+        if include_archived {
+            store.get_including_archived(store::TicketId(id)).await
+        } else {
+            store.get(store::TicketId(id)).await
+        }
+    }
+    .ok_or_else(|| AppError::NotTicket)?;
 
+    set_etag(res, data.version);
     res.render(Json(&data));
 
     Ok(())
 }
 
-#[handler]
-pub async fn patch(res: &mut Response, req: &mut Request) -> AppResult<()> {
+#[endpoint(
+    parameters(
+        ("id" = u64, Path, description = "The ticket id"),
+        ("archived" = Option<bool>, Query, description = "Also look at archived tickets"),
+    ),
+    responses(
+        (status_code = 200, description = "The changes to the ticket, oldest first", body = [HistoryEntry]),
+    ),
+    status_codes(200, 400, 401, 404, 429, 500),
+)]
+pub async fn history(res: &mut Response, req: &mut Request, depot: &mut Depot) -> AppResult<()> {
     let id = req
         .param::<u64>("id")
         .ok_or_else(|| AppError::InvalidTicketId)?;
 
-    let req_data: TicketPatch = req.parse_json().await?;
+    let store = shared_store(depot)?;
 
-    validate_ticket_patch(&req_data)?;
+    let include_archived = parse_flag(req, "archived")?;
 
-    let store = TICKET_STORE
-        .get()
-        .ok_or_else(|| AppError::TicketStoreNotInitialized)?;
+    let data = {
+        let store = store.read().await;
+        if store.is_archived(store::TicketId(id)) && !include_archived {
+            return Err(AppError::NotTicket);
+        }
+        store
+            .history(store::TicketId(id))
+            .ok_or_else(|| AppError::NotTicket)?
+            .to_vec()
+    };
 
-    let ticket = store
-        .read()
-        .await
-        .get(store::TicketId(id))
-        .ok_or_else(|| AppError::NotTicket)?;
+    res.render(Json(&data));
 
-    let data = {
-        let mut data = ticket.write().await;
+    Ok(())
+}
+
+#[endpoint(
+    parameters(
+        ("id" = u64, Path, description = "The ticket id"),
+        ("archived" = Option<bool>, Query, description = "Also look at archived tickets"),
+        ("cursor" = Option<u64>, Query, description = "Start at this comment id"),
+        ("limit" = Option<usize>, Query, description = "The maximum number of items to return"),
+    ),
+    responses(
+        (status_code = 200, description = "A page of comments, oldest first", body = CommentPage),
+    ),
+    status_codes(200, 400, 401, 404, 429, 500),
+)]
+pub async fn comments(res: &mut Response, req: &mut Request, depot: &mut Depot) -> AppResult<()> {
+    let id = req
+        .param::<u64>("id")
+        .ok_or_else(|| AppError::InvalidTicketId)?;
+
+    let store = shared_store(depot)?;
+
+    let include_archived = parse_flag(req, "archived")?;
+
+    let cursor = req
+        .queries()
+        .get("cursor")
+        .map(|cursor| cursor.parse().map(CommentId))
+        .transpose()
+        .map_err(|_| AppError::InvalidQueryParameter("cursor".into()))?;
+
+    let limit = req
+        .queries()
+        .get("limit")
+        .map(|limit| limit.parse())
+        .transpose()
+        .map_err(|_| AppError::InvalidQueryParameter("limit".into()))?;
 
-        if let Some(title) = req_data.title {
-            data.title = title;
+    let page = {
+        let store = store.read().await;
+        if store.is_archived(store::TicketId(id)) && !include_archived {
+            return Err(AppError::NotTicket);
         }
-        if let Some(description) = req_data.description {
-            data.description = description;
+        store
+            .comments(store::TicketId(id), &CommentQuery { cursor, limit })
+            .ok_or_else(|| AppError::NotTicket)?
+    };
+
+    res.render(Json(&page));
+
+    Ok(())
+}
+
+#[endpoint(
+    parameters(
+        ("id" = u64, Path, description = "The ticket id"),
+    ),
+    request_body = CommentDraft,
+    responses(
+        (status_code = 201, description = "The new comment", body = Comment),
+    ),
+    status_codes(201, 400, 401, 403, 404, 409, 413, 422, 429, 500),
+)]
+pub async fn comment(res: &mut Response, req: &mut Request, depot: &mut Depot) -> AppResult<()> {
+    let caller = authorize(depot, Role::Editor)?;
+
+    let id = req
+        .param::<u64>("id")
+        .ok_or_else(|| AppError::InvalidTicketId)?;
+
+    let mut req_data: CommentDraft = parse_body(req).await?;
+    // Authenticated callers can only comment as themselves.
+    if let Some(caller) = caller {
+        req_data.author = caller.user.clone();
+    }
+
+    let store = shared_store(depot)?;
+
+    let data = {
+        store
+            .write()
+            .await
+            .add_comment(store::TicketId(id), req_data)?
+    };
+
+    res.status_code(StatusCode::CREATED);
+    res.render(Json(&data));
+
+    Ok(())
+}
+
+#[endpoint(
+    parameters(
+        ("id" = u64, Path, description = "The ticket id"),
+        ("archived" = Option<bool>, Query, description = "Also look at archived tickets"),
+    ),
+    responses(
+        (status_code = 200, description = "The links from and to the ticket", body = [Link]),
+    ),
+    status_codes(200, 400, 401, 404, 429, 500),
+)]
+pub async fn links(res: &mut Response, req: &mut Request, depot: &mut Depot) -> AppResult<()> {
+    let id = req
+        .param::<u64>("id")
+        .ok_or_else(|| AppError::InvalidTicketId)?;
+
+    let store = shared_store(depot)?;
+
+    let include_archived = parse_flag(req, "archived")?;
+
+    let data = {
+        let store = store.read().await;
+        if store.is_archived(store::TicketId(id)) && !include_archived {
+            return Err(AppError::NotTicket);
         }
-        if let Some(status) = req_data.status {
-            data.status = status;
+        store
+            .links(store::TicketId(id))
+            .ok_or_else(|| AppError::NotTicket)?
+    };
+
+    res.render(Json(&data));
+
+    Ok(())
+}
+
+#[endpoint(
+    parameters(
+        ("id" = u64, Path, description = "The ticket id"),
+    ),
+    request_body = LinkDraft,
+    responses(
+        (status_code = 201, description = "The new link", body = Link),
+    ),
+    status_codes(201, 400, 401, 403, 404, 413, 422, 429, 500),
+)]
+pub async fn create_link(
+    res: &mut Response,
+    req: &mut Request,
+    depot: &mut Depot,
+) -> AppResult<()> {
+    let caller = authorize(depot, Role::Editor)?;
+
+    let id = req
+        .param::<u64>("id")
+        .ok_or_else(|| AppError::InvalidTicketId)?;
+
+    let req_data: LinkDraft = parse_body(req).await?;
+    let link = req_data.from_source(store::TicketId(id));
+
+    let store = shared_store(depot)?;
+
+    let mut store = store.write().await;
+    check_may_change_link(&store, caller, &link).await?;
+    store.link(link)?;
+
+    res.status_code(StatusCode::CREATED);
+    res.render(Json(&link));
+
+    Ok(())
+}
+
+// `DELETE /api/ticket/<id>/links/<kind>/<target>` removes the link from `id` to `target`.
+#[endpoint(
+    parameters(
+        ("id" = u64, Path, description = "The ticket id"),
+        ("kind" = String, Path, description = "`parent`, `blocks` or `duplicate_of`"),
+        ("target" = u64, Path, description = "The id of the linked ticket"),
+    ),
+    responses(
+        (status_code = 204, description = "The link was removed"),
+    ),
+    status_codes(204, 400, 401, 403, 404, 429, 500),
+)]
+pub async fn delete_link(
+    res: &mut Response,
+    req: &mut Request,
+    depot: &mut Depot,
+) -> AppResult<()> {
+    let caller = authorize(depot, Role::Editor)?;
+
+    let id = req
+        .param::<u64>("id")
+        .ok_or_else(|| AppError::InvalidTicketId)?;
+    let kind = req
+        .param::<String>("kind")
+        .and_then(|kind| LinkKind::try_from(kind.as_str()).ok())
+        .ok_or(AppError::NotLink)?;
+    let target = req
+        .param::<u64>("target")
+        .ok_or_else(|| AppError::InvalidTicketId)?;
+
+    let link = LinkDraft {
+        kind,
+        target: store::TicketId(target),
+    }
+    .from_source(store::TicketId(id));
+
+    let store = shared_store(depot)?;
+
+    let mut store = store.write().await;
+    check_may_change_link(&store, caller, &link).await?;
+    store.unlink(link)?;
+
+    res.status_code(StatusCode::NO_CONTENT);
+
+    Ok(())
+}
+
+#[endpoint(
+    parameters(
+        ("status" = Option<Status>, Query, description = "Only tickets with this status"),
+        ("assignee" = Option<String>, Query, description = "Only tickets assigned to this person"),
+        ("tag" = Option<Vec<String>>, Query, description = "Only tickets with these tags"),
+        ("tag_match" = Option<String>, Query, description = "`all` (the default) or `any` of the tags"),
+        ("overdue" = Option<bool>, Query, description = "Only open tickets past their due date"),
+        ("sort" = Option<String>, Query, description = "`id` (the default), `priority` or `due_date`"),
+        ("search" = Option<String>, Query, description = "Only tickets whose title or description contains this text"),
+        ("cursor" = Option<u64>, Query, description = "Start at this id"),
+        ("limit" = Option<usize>, Query, description = "The maximum number of items to return"),
+        ("archived" = Option<bool>, Query, description = "Also look at archived tickets"),
+    ),
+    responses(
+        (status_code = 200, description = "A page of tickets", body = TicketPage),
+    ),
+    status_codes(200, 400, 401, 429, 500),
+)]
+pub async fn list(res: &mut Response, req: &mut Request, depot: &mut Depot) -> AppResult<()> {
+    let query = parse_ticket_query(req)?;
+
+    let store = shared_store(depot)?;
+
+    let page = store.read().await.list(&query).await;
+
+    res.render(Json(&page));
+
+    Ok(())
+}
+
+// `GET /api/ticket/search?q=<words>` ranks tickets by how well they match the words.
+#[endpoint(
+    parameters(
+        ("q" = String, Query, description = "The words to look for"),
+        ("limit" = Option<usize>, Query, description = "The maximum number of items to return"),
+        ("archived" = Option<bool>, Query, description = "Also look at archived tickets"),
+    ),
+    responses(
+        (status_code = 200, description = "The matching tickets, best match first", body = [SearchHit]),
+    ),
+    status_codes(200, 400, 401, 429, 500),
+)]
+pub async fn search_tickets(
+    res: &mut Response,
+    req: &mut Request,
+    depot: &mut Depot,
+) -> AppResult<()> {
+    let text = req
+        .queries()
+        .get("q")
+        .cloned()
+        .ok_or_else(|| AppError::InvalidQueryParameter("q".into()))?;
+
+    let limit = req
+        .queries()
+        .get("limit")
+        .map(|limit| limit.parse())
+        .transpose()
+        .map_err(|_| AppError::InvalidQueryParameter("limit".into()))?;
+
+    let include_archived = parse_flag(req, "archived")?;
+
+    let store = shared_store(depot)?;
+
+    let query = SearchQuery {
+        text,
+        limit,
+        include_archived,
+    };
+    let hits = store.read().await.search(&query).await;
+
+    res.render(Json(&hits));
+
+    Ok(())
+}
+
+// `GET /api/ticket/events` streams ticket changes as server-sent events, until the client disconnects.
+#[endpoint(
+    parameters(
+        ("id" = Option<u64>, Query, description = "Only changes to this ticket"),
+        ("status" = Option<Status>, Query, description = "Only changes leaving a ticket with this status"),
+    ),
+    responses(
+        (status_code = 200, description = "A `created` or `patched` event per change, and a `lagged` event with the number of skipped changes when the client falls behind", body = TicketEvent, content_type = "text/event-stream"),
+    ),
+    status_codes(200, 400, 401, 429, 500),
+)]
+pub async fn events(res: &mut Response, req: &mut Request, depot: &mut Depot) -> AppResult<()> {
+    let id = req
+        .queries()
+        .get("id")
+        .map(|id| id.parse().map(store::TicketId))
+        .transpose()
+        .map_err(|_| AppError::InvalidQueryParameter("id".into()))?;
+
+    let status = req
+        .queries()
+        .get("status")
+        .map(|status| Status::try_from(status.as_str()))
+        .transpose()
+        .map_err(|_| AppError::InvalidQueryParameter("status".into()))?;
+
+    let store = shared_store(depot)?;
+
+    let receiver = store.read().await.subscribe();
+
+    // Open streams would otherwise hold up a graceful shutdown until it times out.
+    let stopping = depot
+        .obtain::<CancellationToken>()
+        .cloned()
+        .unwrap_or_default();
+    let stream =
+        sse_events(receiver, EventFilter { id, status }).take_until(stopping.cancelled_owned());
+    SseKeepAlive::new(stream).stream(res);
+
+    Ok(())
+}
+
+// A client that falls behind gets a `lagged` event instead of the changes it missed,
+// and can catch up on them with regular requests.
+// An event that fails to serialize is skipped, rather than ending the stream.
+fn sse_events(
+    receiver: broadcast::Receiver<TicketEvent>,
+    filter: EventFilter,
+) -> impl Stream<Item = Result<SseEvent, Infallible>> {
+    stream::unfold((receiver, filter), |(mut receiver, filter)| async move {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(event) if filter.matches(&event) => {
+                    match SseEvent::default().name(event.name()).json(&event) {
+                        Ok(event) => event,
+                        Err(_) => continue,
+                    }
+                }
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => SseEvent::default()
+                    .name("lagged")
+                    .text(format!(r#"{{"skipped":{skipped}}}"#)),
+                // The store is gone, so no more changes will come.
+                Err(RecvError::Closed) => return None,
+            };
+            return Some((Ok(event), (receiver, filter)));
         }
+    })
+}
+
+fn parse_ticket_query(req: &Request) -> AppResult<TicketQuery> {
+    let status = req
+        .queries()
+        .get("status")
+        .map(|status| Status::try_from(status.as_str()))
+        .transpose()
+        .map_err(|_| AppError::InvalidQueryParameter("status".into()))?;
 
-        data.to_owned()
+    let assignee = req
+        .queries()
+        .get("assignee")
+        .map(|assignee| TicketAssignee::try_from(assignee.as_str()))
+        .transpose()
+        .map_err(|_| AppError::InvalidQueryParameter("assignee".into()))?;
+
+    // `?tag=a&tag=b` matches tickets with both tags, `&tag_match=any` with either.
+    let tags = req
+        .queries()
+        .get_vec("tag")
+        .map(|tags| {
+            tags.iter()
+                .map(|tag| TicketTag::try_from(tag.as_str()))
+                .collect()
+        })
+        .transpose()
+        .map_err(|_| AppError::InvalidQueryParameter("tag".into()))?
+        .unwrap_or_default();
+
+    let tag_match = req
+        .queries()
+        .get("tag_match")
+        .map(|tag_match| TagMatch::try_from(tag_match.as_str()))
+        .transpose()
+        .map_err(|_| AppError::InvalidQueryParameter("tag_match".into()))?
+        .unwrap_or_default();
+
+    let overdue = parse_flag(req, "overdue")?;
+
+    let sort = req
+        .queries()
+        .get("sort")
+        .map(|sort| TicketSort::try_from(sort.as_str()))
+        .transpose()
+        .map_err(|_| AppError::InvalidQueryParameter("sort".into()))?
+        .unwrap_or_default();
+
+    let search = req
+        .queries()
+        .get("search")
+        .filter(|search| !search.is_empty())
+        .cloned();
+
+    let cursor = req
+        .queries()
+        .get("cursor")
+        .map(|cursor| cursor.parse().map(store::TicketId))
+        .transpose()
+        .map_err(|_| AppError::InvalidQueryParameter("cursor".into()))?;
+
+    let limit = req
+        .queries()
+        .get("limit")
+        .map(|limit| limit.parse())
+        .transpose()
+        .map_err(|_| AppError::InvalidQueryParameter("limit".into()))?;
+
+    let include_archived = parse_flag(req, "archived")?;
+
+    Ok(TicketQuery {
+        status,
+        assignee,
+        tags,
+        tag_match,
+        overdue,
+        sort,
+        search,
+        cursor,
+        limit,
+        include_archived,
+    })
+}
+
+fn parse_flag(req: &Request, key: &str) -> AppResult<bool> {
+    req.queries()
+        .get(key)
+        .map_or(Ok(false), |flag| flag.parse())
+        .map_err(|_| AppError::InvalidQueryParameter(key.into()))
+}
+
+#[endpoint(
+    parameters(
+        ("id" = u64, Path, description = "The ticket id"),
+        ("If-Match" = Option<String>, Header, description = "Only patch the ticket if it is still at this version, e.g. `\"3\"`"),
+    ),
+    request_body = TicketPatch,
+    responses(
+        (status_code = 200, description = "The patched ticket", body = Ticket),
+    ),
+    status_codes(200, 400, 401, 403, 404, 409, 412, 413, 422, 429, 500),
+)]
+pub async fn patch(res: &mut Response, req: &mut Request, depot: &mut Depot) -> AppResult<()> {
+    let caller = authorize(depot, Role::Editor)?;
+
+    let id = req
+        .param::<u64>("id")
+        .ok_or_else(|| AppError::InvalidTicketId)?;
+
+    let expected_version = parse_if_match(req)?;
+
+    let req_data: TicketPatch = parse_body(req).await?;
+
+    let store = shared_store(depot)?;
+
+    let data = {
+        let mut store = store.write().await;
+        check_may_change(&store, caller, store::TicketId(id)).await?;
+        store
+            .patch(store::TicketId(id), req_data, expected_version)
+            .await?
     };
+
+    set_etag(res, data.version);
     res.render(Json(&data));
 
     Ok(())
 }
 
-#[handler]
-pub async fn create(res: &mut Response, req: &mut Request) -> AppResult<()> {
-    let req_data = req.parse_json().await?;
+// Ticket fields are validated while the body is deserialized, and report their
+// own error, so that clients get e.g. `invalid_title` rather than `invalid_json`.
+async fn parse_body<T: DeserializeOwned>(req: &mut Request) -> AppResult<T> {
+    let is_json = req
+        .content_type()
+        .is_some_and(|ctype| ctype.subtype() == mime::JSON);
+    if !is_json {
+        return Err(ParseError::InvalidContentType.into());
+    }
+    let payload = req.payload().await?;
 
-    validate_ticket_draft(&req_data)?;
+    deserialize(|| serde_json::from_slice(payload))
+}
 
-    let store = TICKET_STORE
-        .get()
-        .ok_or_else(|| AppError::TicketStoreNotInitialized)?;
+// Parses one item of a bulk request.
+fn parse_value<T: DeserializeOwned>(item: &serde_json::Value) -> AppResult<T> {
+    deserialize(|| T::deserialize(item))
+}
 
-    let id = { store.write().await.add_ticket(req_data) };
+fn deserialize<T>(parse: impl FnOnce() -> serde_json::Result<T>) -> AppResult<T> {
+    parse().map_err(|e| match FieldError::from_message(&e.to_string()) {
+        Some(field_error) => field_error.into(),
+        None => ParseError::SerdeJson(e).into(),
+    })
+}
 
-    res.render(Json(json!({
-        "id": id.0,
-    })));
+// The ticket version doubles as its entity tag.
+fn set_etag(res: &mut Response, version: u64) {
+    if let Ok(etag) = HeaderValue::from_str(&format!("\"{version}\"")) {
+        res.headers_mut().insert(ETAG, etag);
+    }
+}
+
+// `If-Match: "<version>"` makes the patch conditional, `If-Match: *` or no header doesn't.
+fn parse_if_match(req: &Request) -> AppResult<Option<u64>> {
+    let Some(if_match) = req.headers().get(IF_MATCH) else {
+        return Ok(None);
+    };
+
+    let if_match = if_match
+        .to_str()
+        .map_err(|_| AppError::InvalidIfMatch)?
+        .trim();
+    if if_match == "*" {
+        return Ok(None);
+    }
+
+    if_match
+        .strip_prefix('"')
+        .and_then(|etag| etag.strip_suffix('"'))
+        .and_then(|version| version.parse().ok())
+        .map(Some)
+        .ok_or(AppError::InvalidIfMatch)
+}
+
+// Archives the ticket, unless `?hard=true` asks for it to be removed for good.
+#[endpoint(
+    parameters(
+        ("id" = u64, Path, description = "The ticket id"),
+        ("hard" = Option<bool>, Query, description = "Remove the ticket instead of archiving it"),
+    ),
+    responses(
+        (status_code = 204, description = "The ticket was archived or removed"),
+    ),
+    status_codes(204, 400, 401, 403, 404, 409, 429, 500),
+)]
+pub async fn delete(res: &mut Response, req: &mut Request, depot: &mut Depot) -> AppResult<()> {
+    authorize(depot, Role::Admin)?;
+
+    let id = req
+        .param::<u64>("id")
+        .ok_or_else(|| AppError::InvalidTicketId)?;
+
+    let hard = parse_flag(req, "hard")?;
+
+    let store = shared_store(depot)?;
+
+    {
+        let mut store = store.write().await;
+        if hard {
+            store.remove(store::TicketId(id)).await?;
+        } else {
+            store.archive(store::TicketId(id))?;
+        }
+    }
+
+    res.status_code(StatusCode::NO_CONTENT);
+
+    Ok(())
+}
+
+// `POST /api/ticket/bulk` takes an array of drafts, and returns the id or the error
+// of each of them. With `?atomic=true`, a single failure rejects the whole batch.
+#[endpoint(
+    parameters(
+        ("atomic" = Option<bool>, Query, description = "Reject the whole batch if any item fails"),
+    ),
+    request_body = Vec<TicketDraft>,
+    responses(
+        (status_code = 200, description = "The id or the error of each draft", body = [BulkItem]),
+        (status_code = 422, description = "An atomic batch was rejected, see the errors of its items", body = [BulkItem]),
+    ),
+    status_codes(200, 400, 401, 403, 413, 422, 429, 500),
+)]
+pub async fn bulk_create(
+    res: &mut Response,
+    req: &mut Request,
+    depot: &mut Depot,
+) -> AppResult<()> {
+    let caller = authorize(depot, Role::Editor)?;
+
+    let atomic = parse_flag(req, "atomic")?;
+
+    let items: Vec<serde_json::Value> = req.parse_json().await?;
+    let drafts = items
+        .into_iter()
+        .map(|item| {
+            parse_value(&item).map(|draft| TicketDraft {
+                created_by: caller.map(|caller| caller.user.clone()),
+                ..draft
+            })
+        })
+        .collect();
+
+    let store = shared_store(depot)?;
+
+    let results: Vec<BulkItem> = {
+        let mut store = store.write().await;
+        store
+            .add_tickets(drafts, atomic)
+            .into_iter()
+            .map(BulkItem::from)
+            .collect()
+    };
+
+    render_bulk(res, results, atomic);
+
+    Ok(())
+}
+
+// `PATCH /api/ticket/bulk` takes an array of `{ "id", "patch", "version"? }` items.
+#[endpoint(
+    parameters(
+        ("atomic" = Option<bool>, Query, description = "Reject the whole batch if any item fails"),
+    ),
+    request_body = Vec<BulkPatch>,
+    responses(
+        (status_code = 200, description = "The patched ticket or the error of each item", body = [BulkItem]),
+        (status_code = 422, description = "An atomic batch was rejected, see the errors of its items", body = [BulkItem]),
+    ),
+    status_codes(200, 400, 401, 403, 413, 422, 429, 500),
+)]
+pub async fn bulk_patch(res: &mut Response, req: &mut Request, depot: &mut Depot) -> AppResult<()> {
+    let caller = authorize(depot, Role::Editor)?;
+
+    let atomic = parse_flag(req, "atomic")?;
+
+    let items: Vec<serde_json::Value> = req.parse_json().await?;
+    // Reports errors against the item's id, whenever it can be read.
+    let ids: Vec<_> = items
+        .iter()
+        .map(|item| {
+            item.get("id")
+                .and_then(serde_json::Value::as_u64)
+                .map(store::TicketId)
+        })
+        .collect();
+    let patches: Vec<_> = items.iter().map(parse_value::<BulkPatch>).collect();
+
+    let store = shared_store(depot)?;
+
+    let results: Vec<BulkItem> = {
+        let mut store = store.write().await;
+        let mut checked = Vec::with_capacity(patches.len());
+        for item in patches {
+            checked.push(match item {
+                Ok(item) => check_may_change(&store, caller, item.id)
+                    .await
+                    .map(|()| item),
+                Err(e) => Err(e),
+            });
+        }
+        store
+            .patch_tickets(checked, atomic)
+            .await
+            .into_iter()
+            .zip(ids)
+            .map(|(result, id)| match (result, id) {
+                (Ok(ticket), _) => BulkItem::patched(ticket.id, Ok(ticket)),
+                (Err(e), Some(id)) => BulkItem::patched(id, Err(e)),
+                (Err(e), None) => BulkItem::from(Err(e)),
+            })
+            .collect()
+    };
+
+    render_bulk(res, results, atomic);
 
     Ok(())
 }
 
-pub async fn run(local_addr: &str) -> Result<(), ServerError> {
-    let store = store::TicketStore::new();
-    TICKET_STORE.get_or_init(|| RwLock::new(store));
+// A rejected atomic batch is an error as a whole, a partially applied one isn't.
+fn render_bulk(res: &mut Response, results: Vec<BulkItem>, atomic: bool) {
+    if atomic && !results.iter().all(BulkItem::is_ok) {
+        res.status_code(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    res.render(Json(&results));
+}
 
-    let router = Router::with_path("/api/ticket")
-        .post(create)
-        .push(Router::new().path("/<id>").get(get).patch(patch));
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[salvo(schema(symbol = "CreatedTicket"))]
+pub struct CreatedTicket {
+    pub id: store::TicketId,
+}
 
-    let acceptor = TcpListener::new(local_addr).try_bind().await?;
-    Server::new(acceptor).try_serve(router).await?;
+#[endpoint(
+    request_body = TicketDraft,
+    responses(
+        (status_code = 200, description = "The id of the new ticket", body = CreatedTicket),
+    ),
+    status_codes(200, 400, 401, 403, 413, 422, 429, 500),
+)]
+pub async fn create(res: &mut Response, req: &mut Request, depot: &mut Depot) -> AppResult<()> {
+    let caller = authorize(depot, Role::Editor)?;
+
+    let mut req_data: TicketDraft = parse_body(req).await?;
+    req_data.created_by = caller.map(|caller| caller.user.clone());
+
+    let store = shared_store(depot)?;
+
+    let id = { store.write().await.add_ticket(req_data)? };
+
+    res.render(Json(CreatedTicket { id }));
+
+    Ok(())
+}
+
+// The body size limits are hoops of their own routes, since bulk routes get a larger one.
+fn ticket_router() -> Router {
+    Router::with_path("/api/ticket")
+        .oapi_security(SecurityRequirement::new("bearer", Vec::<String>::new()))
+        // These have to come before `<id>`, which would match any path segment.
+        .push(
+            Router::with_path("bulk")
+                .hoop(limit_bulk_body_size)
+                .post(bulk_create)
+                .patch(bulk_patch),
+        )
+        .push(
+            Router::new()
+                .hoop(limit_body_size)
+                .get(list)
+                .post(create)
+                .push(Router::with_path("search").get(search_tickets))
+                .push(Router::with_path("events").get(events))
+                .push(
+                    Router::new()
+                        .path("/<id>")
+                        .get(get)
+                        .patch(patch)
+                        .delete(delete)
+                        .push(Router::with_path("history").get(history))
+                        .push(Router::with_path("comments").get(comments).post(comment))
+                        .push(
+                            Router::with_path("links")
+                                .get(links)
+                                .post(create_link)
+                                .push(Router::with_path("<kind>/<target>").delete(delete_link)),
+                        ),
+                ),
+        )
+}
+
+// Event streams end once `stopping` is cancelled.
+pub fn router(
+    store: SharedStore,
+    config: &ServerConfig,
+    clock: Arc<dyn Clock>,
+    stopping: CancellationToken,
+) -> Router {
+    let mut affixes = affix::inject(store)
+        .inject(config.auth.clone())
+        .inject(config.limits.clone())
+        .inject(stopping);
+    if let Some(rate) = config.limits.rate {
+        affixes = affixes.inject(Arc::new(RateLimiter::new(rate, clock)));
+    }
+
+    Router::new()
+        .push(
+            ticket_router()
+                .hoop(affixes)
+                .hoop(throttle_address)
+                .hoop(authenticate)
+                .hoop(throttle_user),
+        )
+        .push(openapi().into_router("/api/openapi.json"))
+}
+
+// Describes the routes of `router`, from the `#[endpoint]` attributes of the handlers.
+pub fn openapi() -> OpenApi {
+    OpenApi::new("Ticket API", env!("CARGO_PKG_VERSION"))
+        .add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+        )
+        .merge_router(&ticket_router())
+}
+
+// How long in-flight requests get to complete once shutdown has been requested.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Serves on an already bound listener, so that callers can bind to port 0
+// and read the actual address before the server starts.
+//
+// Once `shutdown` completes, the server stops accepting connections, drains
+// the in-flight requests and flushes the store before returning.
+pub async fn run(
+    listener: tokio::net::TcpListener,
+    store: SharedStore,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<(), ServerError> {
+    run_with_config(listener, store, &ServerConfig::default(), shutdown).await
+}
+
+// Like `run`, with the settings in `config`.
+// The store sections that are set replace the policy or workflow `store` was built with.
+pub async fn run_with_config(
+    listener: tokio::net::TcpListener,
+    store: SharedStore,
+    config: &ServerConfig,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<(), ServerError> {
+    let acceptor = TcpAcceptor::try_from(listener)?;
+    let server = Server::new(acceptor);
+
+    let handle = server.handle();
+    let stopping = CancellationToken::new();
+    let stop = stopping.clone();
+    tokio::spawn(async move {
+        shutdown.await;
+        stop.cancel();
+        handle.stop_graceful(SHUTDOWN_TIMEOUT);
+    });
+
+    let clock = {
+        let mut store = store.write().await;
+        config.configure(&mut store)?;
+        store.clock()
+    };
+    server
+        .try_serve(router(store.clone(), config, clock, stopping))
+        .await?;
+    store.read().await.flush()?;
 
     Ok(())
 }
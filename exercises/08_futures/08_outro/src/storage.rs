@@ -0,0 +1,245 @@
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::comment::Comment;
+use crate::data::{Ticket, TicketPatch};
+use crate::link::Link;
+use crate::store::TicketId;
+
+// Every change to the store is described by an event.
+// Replaying the events in order rebuilds the store.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum StoreEvent {
+    Created {
+        ticket: Ticket,
+        #[serde(default)]
+        at: DateTime<Utc>,
+    },
+    Patched {
+        id: TicketId,
+        patch: TicketPatch,
+        #[serde(default)]
+        at: DateTime<Utc>,
+    },
+    Archived {
+        id: TicketId,
+    },
+    Removed {
+        id: TicketId,
+    },
+    Commented {
+        comment: Comment,
+    },
+    Linked {
+        link: Link,
+    },
+    Unlinked {
+        link: Link,
+    },
+}
+
+pub trait Storage: Send + Sync {
+    // Returns every event recorded so far, oldest first.
+    fn load(&self) -> io::Result<Vec<StoreEvent>>;
+
+    fn append(&self, event: &StoreEvent) -> io::Result<()>;
+
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Keeps nothing: a store built on top of it starts empty every time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MemoryStorage;
+
+impl Storage for MemoryStorage {
+    fn load(&self) -> io::Result<Vec<StoreEvent>> {
+        Ok(Vec::new())
+    }
+
+    fn append(&self, _event: &StoreEvent) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// An append-only write-ahead log, one JSON encoded event per line.
+pub struct LogStorage {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl LogStorage {
+    pub const FILE_NAME: &'static str = "tickets.log";
+
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        let path = dir.join(Self::FILE_NAME);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+        // Held until the storage is dropped, so that no other process (another server,
+        // or the `ticket_store` tool) appends to or truncates the log at the same time.
+        file.try_lock().map_err(|e| match e {
+            TryLockError::WouldBlock => io::Error::new(
+                io::ErrorKind::WouldBlock,
+                format!("{} is in use by another process", path.display()),
+            ),
+            TryLockError::Error(e) => e,
+        })?;
+        truncate_torn_tail(&mut file)?;
+
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Storage for LogStorage {
+    fn load(&self) -> io::Result<Vec<StoreEvent>> {
+        let reader = BufReader::new(File::open(&self.path)?);
+
+        let mut events = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event = serde_json::from_str(&line)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            events.push(event);
+        }
+
+        Ok(events)
+    }
+
+    fn append(&self, event: &StoreEvent) -> io::Result<()> {
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+
+        let mut file = self
+            .file
+            .lock()
+            .map_err(|_| io::Error::other("log file lock is poisoned"))?;
+        file.write_all(&line)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let mut file = self
+            .file
+            .lock()
+            .map_err(|_| io::Error::other("log file lock is poisoned"))?;
+        file.flush()?;
+        file.sync_data()
+    }
+}
+
+// A crash in the middle of `append` can leave a partial line at the end of the log.
+// Drop it, so that the next event starts on a fresh line.
+fn truncate_torn_tail(file: &mut File) -> io::Result<()> {
+    let mut content = Vec::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_end(&mut content)?;
+
+    if content.last().is_some_and(|&byte| byte != b'\n') {
+        let valid_len = content
+            .iter()
+            .rposition(|&byte| byte == b'\n')
+            .map_or(0, |pos| pos + 1);
+        file.set_len(valid_len as u64)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Status, TicketDescription, TicketDraft, TicketTitle};
+
+    fn created(id: u64) -> StoreEvent {
+        StoreEvent::Created {
+            ticket: ticket(id),
+            at: DateTime::UNIX_EPOCH,
+        }
+    }
+
+    fn ticket(id: u64) -> Ticket {
+        let draft = TicketDraft::new(
+            TicketTitle::try_from("A title").unwrap(),
+            TicketDescription::try_from("A description").unwrap(),
+        );
+        Ticket::from_draft(TicketId(id), draft)
+    }
+
+    #[test]
+    fn test_log_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let events = vec![
+            created(0),
+            StoreEvent::Patched {
+                id: TicketId(0),
+                patch: TicketPatch::new(None, None, Some(Status::Done)).unwrap(),
+                at: DateTime::UNIX_EPOCH,
+            },
+        ];
+
+        let storage = LogStorage::open(dir.path()).unwrap();
+        for event in &events {
+            storage.append(event).unwrap();
+        }
+        storage.flush().unwrap();
+        drop(storage);
+
+        let storage = LogStorage::open(dir.path()).unwrap();
+        assert_eq!(storage.load().unwrap(), events);
+    }
+
+    #[test]
+    fn test_torn_tail_is_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = LogStorage::open(dir.path()).unwrap();
+        storage.append(&created(0)).unwrap();
+        drop(storage);
+
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(dir.path().join(LogStorage::FILE_NAME))
+            .unwrap();
+        file.write_all(br#"{"event":"created","tic"#).unwrap();
+        drop(file);
+
+        let storage = LogStorage::open(dir.path()).unwrap();
+        storage.append(&created(1)).unwrap();
+
+        let events = storage.load().unwrap();
+        assert_eq!(events, vec![created(0), created(1),]);
+    }
+
+    #[test]
+    fn test_log_is_locked_while_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = LogStorage::open(dir.path()).unwrap();
+
+        let err = LogStorage::open(dir.path()).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+
+        drop(storage);
+        LogStorage::open(dir.path()).unwrap();
+    }
+}
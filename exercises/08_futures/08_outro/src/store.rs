@@ -1,49 +1,753 @@
-use crate::data::{Status, Ticket, TicketDraft};
+use crate::bulk::BulkPatch;
+use crate::clock::{Clock, SystemClock};
+use crate::comment::{
+    validate_comment_draft, Comment, CommentDraft, CommentId, CommentPage, CommentQuery,
+};
+use crate::data::{
+    validate_ticket, validate_ticket_draft, validate_ticket_patch, SearchHit, SearchQuery, Status,
+    Ticket, TicketDraft, TicketPage, TicketPatch, TicketQuery, TicketSort,
+};
+use crate::error::{AppError, AppResult};
+use crate::feed::{self, TicketEvent};
+use crate::history::{HistoryEntry, TicketChange};
+use crate::index::{SearchIndex, TagIndex};
+use crate::link::{Link, LinkKind, TicketLinks};
+use crate::storage::{MemoryStorage, Storage, StoreEvent};
+use crate::transfer::{read_tickets, write_tickets, ExportedTicket, TransferFormat};
+use crate::workflow::StatusWorkflow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, BufRead, Write};
 use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::{collections::BTreeMap, sync::atomic::Ordering};
 
-use tokio::sync::RwLock;
+use chrono::{DateTime, Utc};
+use ticket_fields::ValidationPolicy;
+use tokio::sync::{broadcast, RwLock};
 
+use salvo::oapi::ToSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, ToSchema)]
+#[salvo(schema(symbol = "TicketId"))]
 pub struct TicketId(pub u64);
 
 pub struct TicketStore {
     tickets: BTreeMap<TicketId, Arc<RwLock<Ticket>>>,
+    // Archived tickets stay in `tickets`, but are hidden unless explicitly asked for.
+    archived: BTreeSet<TicketId>,
+    history: BTreeMap<TicketId, Vec<HistoryEntry>>,
+    tags: TagIndex,
+    search: SearchIndex,
+    comments: BTreeMap<TicketId, BTreeMap<CommentId, Comment>>,
+    links: TicketLinks,
     counter: AtomicU64,
+    comment_counter: AtomicU64,
+    storage: Box<dyn Storage>,
+    workflow: StatusWorkflow,
+    policy: ValidationPolicy,
+    clock: Arc<dyn Clock>,
+    events: broadcast::Sender<TicketEvent>,
 }
 
 impl TicketStore {
     pub fn new() -> Self {
         Self {
             tickets: BTreeMap::new(),
+            archived: BTreeSet::new(),
+            history: BTreeMap::new(),
+            tags: TagIndex::default(),
+            search: SearchIndex::default(),
+            comments: BTreeMap::new(),
+            links: TicketLinks::default(),
             counter: AtomicU64::new(0),
+            comment_counter: AtomicU64::new(0),
+            storage: Box::new(MemoryStorage),
+            workflow: StatusWorkflow::default(),
+            policy: ValidationPolicy::default(),
+            clock: Arc::new(SystemClock),
+            events: feed::channel(),
         }
     }
 
-    pub fn add_ticket(&mut self, ticket: TicketDraft) -> TicketId {
+    // Builds a store on top of `storage`, replaying the events it already holds.
+    pub fn open(storage: impl Storage + 'static) -> io::Result<Self> {
+        let unknown_ticket = |id: TicketId| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("event for unknown ticket {}", id.0),
+            )
+        };
+
+        let mut tickets = BTreeMap::new();
+        let mut archived = BTreeSet::new();
+        let mut history = BTreeMap::new();
+        let mut comments: BTreeMap<_, BTreeMap<_, _>> = BTreeMap::new();
+        let mut counter = 0;
+        let mut comment_counter = 0;
+        let mut links = TicketLinks::default();
+        for event in storage.load()? {
+            match event {
+                StoreEvent::Created { ticket, at } => {
+                    counter = counter.max(ticket.id.0 + 1);
+                    record(&mut history, ticket.id, at, [created(&ticket)]);
+                    tickets.insert(ticket.id, ticket);
+                }
+                StoreEvent::Patched { id, patch, at } => {
+                    let ticket = tickets.get_mut(&id).ok_or_else(|| unknown_ticket(id))?;
+                    record(&mut history, id, at, ticket.apply(patch));
+                }
+                StoreEvent::Archived { id } => {
+                    if !tickets.contains_key(&id) {
+                        return Err(unknown_ticket(id));
+                    }
+                    archived.insert(id);
+                }
+                StoreEvent::Removed { id } => {
+                    tickets.remove(&id).ok_or_else(|| unknown_ticket(id))?;
+                    archived.remove(&id);
+                    history.remove(&id);
+                    comments.remove(&id);
+                    links.remove_ticket(id);
+                }
+                StoreEvent::Commented { comment } => {
+                    if !tickets.contains_key(&comment.ticket_id) {
+                        return Err(unknown_ticket(comment.ticket_id));
+                    }
+                    comment_counter = comment_counter.max(comment.id.0 + 1);
+                    comments
+                        .entry(comment.ticket_id)
+                        .or_default()
+                        .insert(comment.id, comment);
+                }
+                StoreEvent::Linked { link } => {
+                    for id in [link.source, link.target] {
+                        if !tickets.contains_key(&id) {
+                            return Err(unknown_ticket(id));
+                        }
+                    }
+                    links.insert(link);
+                }
+                StoreEvent::Unlinked { link } => {
+                    links.remove(&link);
+                }
+            }
+        }
+
+        let mut tags = TagIndex::default();
+        let mut search = SearchIndex::default();
+        for ticket in tickets.values() {
+            tags.insert(ticket.id, &ticket.tags);
+            search.insert(ticket);
+        }
+
+        let tickets = tickets
+            .into_iter()
+            .map(|(id, ticket)| (id, Arc::new(RwLock::new(ticket))))
+            .collect();
+
+        Ok(Self {
+            tickets,
+            archived,
+            history,
+            tags,
+            search,
+            comments,
+            links,
+            counter: AtomicU64::new(counter),
+            comment_counter: AtomicU64::new(comment_counter),
+            storage: Box::new(storage),
+            workflow: StatusWorkflow::default(),
+            policy: ValidationPolicy::default(),
+            clock: Arc::new(SystemClock),
+            events: feed::channel(),
+        })
+    }
+
+    // Only applies to patches made from now on:
+    // replaying the storage never rejects a recorded transition.
+    pub fn with_workflow(mut self, workflow: StatusWorkflow) -> Self {
+        self.workflow = workflow;
+        self
+    }
+
+    // Like the workflow, the policy is only enforced on new drafts and patches.
+    // Stored tickets are read back against the default policy, though,
+    // so the policy can only tighten it.
+    pub fn with_policy(mut self, policy: ValidationPolicy) -> io::Result<Self> {
+        self.set_policy(policy)?;
+        Ok(self)
+    }
+
+    pub fn set_workflow(&mut self, workflow: StatusWorkflow) {
+        self.workflow = workflow;
+    }
+
+    pub fn set_policy(&mut self, policy: ValidationPolicy) -> io::Result<()> {
+        if !policy.is_within(&ValidationPolicy::DEFAULT) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the validation policy must not be looser than the default one",
+            ));
+        }
+        self.policy = policy;
+        Ok(())
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    // Receives the events published from now on, see `TicketEvent`.
+    pub fn subscribe(&self) -> broadcast::Receiver<TicketEvent> {
+        self.events.subscribe()
+    }
+
+    fn publish(&self, event: TicketEvent) {
+        // Sending only fails when nobody is subscribed, which is fine.
+        let _ = self.events.send(event);
+    }
+
+    pub fn add_ticket(&mut self, ticket: TicketDraft) -> AppResult<TicketId> {
+        let ticket = validate_ticket_draft(ticket, &self.policy)?;
+
         let id = TicketId(self.counter.load(Ordering::Relaxed));
+        let ticket = Ticket::from_draft(id, ticket);
+
+        let at = self.clock.now();
+        self.storage.append(&StoreEvent::Created {
+            ticket: ticket.clone(),
+            at,
+        })?;
         self.counter.fetch_add(1, Ordering::Release);
-        let ticket = Ticket {
-            id,
-            title: ticket.title,
-            description: ticket.description,
-            status: Status::ToDo,
-        };
+        record(&mut self.history, id, at, [created(&ticket)]);
+        self.tags.insert(id, &ticket.tags);
+        self.search.insert(&ticket);
+        self.publish(TicketEvent::Created {
+            ticket: ticket.clone(),
+            at,
+        });
 
         let ticket = Arc::new(RwLock::new(ticket));
         self.tickets.insert(id, ticket);
 
-        id
+        Ok(id)
+    }
+
+    // Returns a copy of the ticket: changes have to go through `patch`,
+    // so that they are stored, recorded in the history and indexed.
+    pub async fn get(&self, id: TicketId) -> Option<Ticket> {
+        Some(self.live(id)?.read().await.to_owned())
+    }
+
+    pub async fn get_including_archived(&self, id: TicketId) -> Option<Ticket> {
+        Some(self.tickets.get(&id)?.read().await.to_owned())
+    }
+
+    fn live(&self, id: TicketId) -> Option<&Arc<RwLock<Ticket>>> {
+        if self.archived.contains(&id) {
+            return None;
+        }
+        self.tickets.get(&id)
+    }
+
+    pub fn is_archived(&self, id: TicketId) -> bool {
+        self.archived.contains(&id)
+    }
+
+    pub async fn list(&self, query: &TicketQuery) -> TicketPage {
+        let limit = query.limit();
+        let today = self.clock.now().date_naive();
+        // In id order the scan can start right at the cursor and stop after
+        // one page, any other order needs every match before it can be paged.
+        let by_id = query.sort == TicketSort::Id;
+        let start = match query.cursor {
+            Some(cursor) if by_id => cursor,
+            _ => TicketId(0),
+        };
+
+        // Tag queries only visit the tickets the index returns.
+        let candidates: Box<dyn Iterator<Item = (&TicketId, &Arc<RwLock<Ticket>>)> + Send> =
+            match self.tags.lookup(&query.tags, query.tag_match) {
+                Some(ids) => Box::new(
+                    ids.into_iter()
+                        .filter(move |id| *id >= start)
+                        .filter_map(|id| self.tickets.get_key_value(&id)),
+                ),
+                None => Box::new(self.tickets.range(start..)),
+            };
+
+        let mut tickets = Vec::new();
+        for (id, ticket) in candidates {
+            if !query.include_archived && self.archived.contains(id) {
+                continue;
+            }
+            let ticket = ticket.read().await;
+            if !query.matches(&ticket, today) {
+                continue;
+            }
+            tickets.push(ticket.to_owned());
+            if by_id && tickets.len() > limit {
+                break;
+            }
+        }
+
+        if !by_id {
+            tickets.sort_by(|a, b| query.sort.compare(a, b));
+            // The page starts where the cursor ticket sorts. If it has been
+            // removed in the meantime, the listing starts over.
+            if let Some(cursor) = query.cursor.and_then(|id| self.tickets.get(&id)) {
+                let cursor = cursor.read().await;
+                let skip = tickets.partition_point(|t| query.sort.compare(t, &cursor).is_lt());
+                tickets.drain(..skip);
+            }
+        }
+
+        let next_cursor = tickets.get(limit).map(|ticket| ticket.id);
+        tickets.truncate(limit);
+
+        TicketPage {
+            tickets,
+            next_cursor,
+        }
+    }
+
+    pub async fn search(&self, query: &SearchQuery) -> Vec<SearchHit> {
+        let mut hits = Vec::new();
+        for (id, score) in self.search.search(&query.text) {
+            if hits.len() == query.limit() {
+                break;
+            }
+            if !query.include_archived && self.archived.contains(&id) {
+                continue;
+            }
+            if let Some(ticket) = self.tickets.get(&id) {
+                let ticket = ticket.read().await.to_owned();
+                hits.push(SearchHit { ticket, score });
+            }
+        }
+        hits
+    }
+
+    // When `expected_version` is set, the patch is only applied if nobody
+    // else has modified the ticket since that version was read.
+    pub async fn patch(
+        &mut self,
+        id: TicketId,
+        patch: TicketPatch,
+        expected_version: Option<u64>,
+    ) -> AppResult<Ticket> {
+        let ticket = self.live(id).ok_or(AppError::NotTicket)?.clone();
+        let mut ticket = ticket.write().await;
+
+        let patch = self
+            .check_patch(&ticket, patch, expected_version, &BTreeMap::new())
+            .await?;
+
+        // A patch that changes nothing is not written down, nor published.
+        let mut patched = ticket.clone();
+        let changes = patched.apply(patch.clone());
+        if changes.is_empty() {
+            return Ok(ticket.to_owned());
+        }
+
+        let at = self.clock.now();
+        self.storage.append(&StoreEvent::Patched {
+            id,
+            patch: patch.clone(),
+            at,
+        })?;
+        let tags = std::mem::replace(&mut *ticket, patched).tags;
+        let reindex = patch.title.is_some() || patch.description.is_some();
+        record(&mut self.history, id, at, changes);
+        if tags != ticket.tags {
+            self.tags.remove(id, &tags);
+            self.tags.insert(id, &ticket.tags);
+        }
+        if reindex {
+            self.search.insert(&ticket);
+        }
+        self.publish(TicketEvent::Patched {
+            ticket: ticket.to_owned(),
+            at,
+        });
+
+        Ok(ticket.to_owned())
+    }
+
+    // Returns the patch normalized, if it can be applied to `ticket`.
+    // Blockers are looked up in `scratch` first, see `try_patch`.
+    async fn check_patch(
+        &self,
+        ticket: &Ticket,
+        patch: TicketPatch,
+        expected_version: Option<u64>,
+        scratch: &BTreeMap<TicketId, Ticket>,
+    ) -> AppResult<TicketPatch> {
+        if let Some(expected) = expected_version {
+            if expected != ticket.version {
+                return Err(AppError::VersionMismatch {
+                    expected,
+                    actual: ticket.version,
+                });
+            }
+        }
+
+        let patch = validate_ticket_patch(patch, ticket, &self.workflow, &self.policy)?;
+
+        if patch.status == Some(Status::Done) && ticket.status != Status::Done {
+            let blockers = self.unresolved_blockers(ticket.id, scratch).await;
+            if !blockers.is_empty() {
+                return Err(AppError::TicketBlocked(blockers));
+            }
+        }
+
+        Ok(patch)
+    }
+
+    // Items that could not even be parsed are passed in as errors,
+    // so that they count against an `atomic` batch.
+    //
+    // With `atomic`, every draft is validated before the first ticket is
+    // created: if any of them fails, none are created and no id is allocated.
+    pub fn add_tickets(
+        &mut self,
+        drafts: Vec<AppResult<TicketDraft>>,
+        atomic: bool,
+    ) -> Vec<AppResult<TicketId>> {
+        if atomic {
+            let drafts: Vec<_> = drafts
+                .into_iter()
+                .map(|draft| draft.and_then(|draft| validate_ticket_draft(draft, &self.policy)))
+                .collect();
+            if drafts.iter().any(Result::is_err) {
+                return reject_batch(drafts);
+            }
+            return drafts
+                .into_iter()
+                .map(|draft| draft.and_then(|draft| self.add_ticket(draft)))
+                .collect();
+        }
+
+        drafts
+            .into_iter()
+            .map(|draft| draft.and_then(|draft| self.add_ticket(draft)))
+            .collect()
+    }
+
+    // Patches are applied in order, so several of them can target the same ticket.
+    //
+    // With `atomic`, the patches are first tried out on copies of the tickets:
+    // if any of them fails, none are applied. Each patch is still written to
+    // storage on its own, so an I/O error halfway leaves the batch partly applied.
+    pub async fn patch_tickets(
+        &mut self,
+        patches: Vec<AppResult<BulkPatch>>,
+        atomic: bool,
+    ) -> Vec<AppResult<Ticket>> {
+        if atomic {
+            let mut scratch = BTreeMap::new();
+            let mut outcomes = Vec::with_capacity(patches.len());
+            for item in &patches {
+                let outcome = match item {
+                    Ok(item) => self.try_patch(&mut scratch, item).await,
+                    Err(_) => Err(AppError::BatchRejected),
+                };
+                outcomes.push(outcome);
+            }
+            if outcomes.iter().any(Result::is_err) {
+                let outcomes = patches
+                    .into_iter()
+                    .zip(outcomes)
+                    .map(|(item, outcome)| item.and(outcome));
+                return reject_batch(outcomes.collect());
+            }
+        }
+
+        let mut results = Vec::with_capacity(patches.len());
+        for item in patches {
+            let result = match item {
+                Ok(item) => self.patch(item.id, item.patch, item.version).await,
+                Err(e) => Err(e),
+            };
+            results.push(result);
+        }
+        results
+    }
+
+    // Applies the patch to the copy of its ticket in `scratch`, leaving the store untouched.
+    async fn try_patch(
+        &self,
+        scratch: &mut BTreeMap<TicketId, Ticket>,
+        item: &BulkPatch,
+    ) -> AppResult<()> {
+        let mut ticket = match scratch.remove(&item.id) {
+            Some(ticket) => ticket,
+            None => self.get(item.id).await.ok_or(AppError::NotTicket)?,
+        };
+
+        let result = self
+            .check_patch(&ticket, item.patch.clone(), item.version, scratch)
+            .await;
+        if let Ok(patch) = &result {
+            ticket.apply(patch.clone());
+        }
+        scratch.insert(item.id, ticket);
+
+        result.map(|_| ())
+    }
+
+    // Soft delete: the ticket is hidden from reads, but kept around.
+    pub fn archive(&mut self, id: TicketId) -> AppResult<()> {
+        if self.archived.contains(&id) {
+            return Err(AppError::TicketArchived);
+        }
+        if !self.tickets.contains_key(&id) {
+            return Err(AppError::NotTicket);
+        }
+
+        self.storage.append(&StoreEvent::Archived { id })?;
+        self.archived.insert(id);
+
+        Ok(())
+    }
+
+    // Hard delete: works on both live and archived tickets.
+    pub async fn remove(&mut self, id: TicketId) -> AppResult<Ticket> {
+        if !self.tickets.contains_key(&id) {
+            return Err(AppError::NotTicket);
+        }
+
+        self.storage.append(&StoreEvent::Removed { id })?;
+        self.archived.remove(&id);
+        self.history.remove(&id);
+        self.comments.remove(&id);
+        self.links.remove_ticket(id);
+        let ticket = self.tickets.remove(&id).ok_or(AppError::NotTicket)?;
+
+        let ticket = ticket.read().await.to_owned();
+        self.tags.remove(id, &ticket.tags);
+        self.search.remove(id);
+        Ok(ticket)
+    }
+
+    // Oldest change first. Archived tickets keep their history, removed ones don't.
+    pub fn history(&self, id: TicketId) -> Option<&[HistoryEntry]> {
+        self.history.get(&id).map(Vec::as_slice)
     }
 
-    // The `get` method should return a handle to the ticket
-    // which allows the caller to either read or modify the ticket.
-    pub fn get(&self, id: TicketId) -> Option<Arc<RwLock<Ticket>>> {
-        self.tickets.get(&id).cloned()
+    // Comments can only be added to live tickets.
+    pub fn add_comment(&mut self, id: TicketId, comment: CommentDraft) -> AppResult<Comment> {
+        if self.live(id).is_none() {
+            return Err(AppError::NotTicket);
+        }
+        let comment = validate_comment_draft(comment, &self.policy)?;
+
+        let comment = Comment {
+            id: CommentId(self.comment_counter.load(Ordering::Relaxed)),
+            ticket_id: id,
+            author: comment.author,
+            body: comment.body,
+            at: self.clock.now(),
+        };
+        self.storage.append(&StoreEvent::Commented {
+            comment: comment.clone(),
+        })?;
+        self.comment_counter.fetch_add(1, Ordering::Release);
+        self.comments
+            .entry(id)
+            .or_default()
+            .insert(comment.id, comment.clone());
+
+        Ok(comment)
     }
+
+    // Like the history, comments are kept when a ticket is archived
+    // and dropped when it is removed.
+    pub fn comments(&self, id: TicketId, query: &CommentQuery) -> Option<CommentPage> {
+        if !self.tickets.contains_key(&id) {
+            return None;
+        }
+
+        let limit = query.limit();
+        let start = query.cursor.unwrap_or(CommentId(0));
+        let mut comments: Vec<_> = self
+            .comments
+            .get(&id)
+            .into_iter()
+            .flat_map(|comments| comments.range(start..))
+            .map(|(_, comment)| comment.clone())
+            .take(limit + 1)
+            .collect();
+
+        let next_cursor = comments.get(limit).map(|comment| comment.id);
+        comments.truncate(limit);
+
+        Some(CommentPage {
+            comments,
+            next_cursor,
+        })
+    }
+
+    // Both tickets have to be live.
+    pub fn link(&mut self, link: Link) -> AppResult<()> {
+        if self.live(link.source).is_none() || self.live(link.target).is_none() {
+            return Err(AppError::NotTicket);
+        }
+        self.links.check(&link)?;
+
+        self.storage.append(&StoreEvent::Linked { link })?;
+        self.links.insert(link);
+
+        Ok(())
+    }
+
+    pub fn unlink(&mut self, link: Link) -> AppResult<()> {
+        if !self.links.of(link.source).contains(&link) {
+            return Err(AppError::NotLink);
+        }
+
+        self.storage.append(&StoreEvent::Unlinked { link })?;
+        self.links.remove(&link);
+
+        Ok(())
+    }
+
+    // The links from or to the ticket, including those to archived tickets.
+    pub fn links(&self, id: TicketId) -> Option<Vec<Link>> {
+        self.tickets.contains_key(&id).then(|| self.links.of(id))
+    }
+
+    // A blocker is resolved once it is done or archived.
+    // Its copy in `scratch`, if any, takes precedence over the store.
+    async fn unresolved_blockers(
+        &self,
+        id: TicketId,
+        scratch: &BTreeMap<TicketId, Ticket>,
+    ) -> Vec<TicketId> {
+        let mut blockers = Vec::new();
+        for blocker in self.links.sources(id, LinkKind::Blocks) {
+            let status = match scratch.get(&blocker) {
+                Some(ticket) => ticket.status,
+                None => match self.live(blocker) {
+                    Some(ticket) => ticket.read().await.status,
+                    None => continue,
+                },
+            };
+            if status != Status::Done {
+                blockers.push(blocker);
+            }
+        }
+        blockers
+    }
+
+    // Writes every ticket, archived ones included, in id order.
+    pub async fn export(&self, format: TransferFormat, writer: impl Write) -> io::Result<usize> {
+        let mut tickets = Vec::with_capacity(self.tickets.len());
+        for (id, ticket) in &self.tickets {
+            tickets.push(ExportedTicket {
+                ticket: ticket.read().await.to_owned(),
+                archived: self.archived.contains(id),
+            });
+        }
+
+        write_tickets(format, &tickets, writer)
+    }
+
+    // Adds the tickets of an export, keeping their ids. Nothing is imported
+    // unless every ticket is valid and none of their ids is already taken.
+    // New tickets get ids past the highest imported one.
+    pub fn import(&mut self, format: TransferFormat, reader: impl BufRead) -> io::Result<usize> {
+        let tickets = read_tickets(format, reader)?;
+
+        let mut ids = BTreeSet::new();
+        for ExportedTicket { ticket, .. } in &tickets {
+            validate_ticket(ticket, &self.policy).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("ticket {}: {e}", ticket.id.0),
+                )
+            })?;
+            if self.tickets.contains_key(&ticket.id) || !ids.insert(ticket.id) {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("ticket {} already exists", ticket.id.0),
+                ));
+            }
+        }
+
+        let count = tickets.len();
+        for ExportedTicket { ticket, archived } in tickets {
+            let id = ticket.id;
+            let at = self.clock.now();
+            self.storage.append(&StoreEvent::Created {
+                ticket: ticket.clone(),
+                at,
+            })?;
+            if archived {
+                self.storage.append(&StoreEvent::Archived { id })?;
+                self.archived.insert(id);
+            }
+            self.counter.fetch_max(id.0 + 1, Ordering::Release);
+            record(&mut self.history, id, at, [created(&ticket)]);
+            self.tags.insert(id, &ticket.tags);
+            self.search.insert(&ticket);
+            // Archived tickets are hidden from reads, so they are left out of the feed too.
+            if !archived {
+                self.publish(TicketEvent::Created {
+                    ticket: ticket.clone(),
+                    at,
+                });
+            }
+            self.tickets.insert(id, Arc::new(RwLock::new(ticket)));
+        }
+
+        Ok(count)
+    }
+
+    pub fn flush(&self) -> io::Result<()> {
+        self.storage.flush()
+    }
+}
+
+fn created(ticket: &Ticket) -> TicketChange {
+    TicketChange::Created {
+        title: ticket.title.clone(),
+        description: ticket.description.clone(),
+        assignee: ticket.assignee.clone(),
+        tags: ticket.tags.clone(),
+        priority: ticket.priority,
+        due_date: ticket.due_date,
+    }
+}
+
+// Keeps the errors of a rejected batch, and marks every other item as not applied.
+fn reject_batch<T, U>(items: Vec<AppResult<T>>) -> Vec<AppResult<U>> {
+    items
+        .into_iter()
+        .map(|item| match item {
+            Ok(_) => Err(AppError::BatchRejected),
+            Err(e) => Err(e),
+        })
+        .collect()
+}
+
+fn record(
+    history: &mut BTreeMap<TicketId, Vec<HistoryEntry>>,
+    id: TicketId,
+    at: DateTime<Utc>,
+    changes: impl IntoIterator<Item = TicketChange>,
+) {
+    history.entry(id).or_default().extend(
+        changes
+            .into_iter()
+            .map(|change| HistoryEntry { at, change }),
+    );
 }
 
 impl Default for TicketStore {
@@ -51,3 +755,752 @@ impl Default for TicketStore {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bulk::BulkPatch;
+    use crate::clock::FixedClock;
+    use crate::data::{Priority, TagMatch, TicketTag};
+    use crate::error::{TicketLinkError, TicketPatchError, TicketStatusError, TicketTitleError};
+    use crate::storage::LogStorage;
+    use chrono::{Duration, NaiveDate};
+    use ticket_fields::Normalization;
+
+    fn draft(title: &str) -> TicketDraft {
+        TicketDraft::new(
+            title.try_into().unwrap(),
+            "A description".try_into().unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_tickets_survive_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut store = TicketStore::open(LogStorage::open(dir.path()).unwrap()).unwrap();
+        let first = store.add_ticket(draft("First")).unwrap();
+        let second = store.add_ticket(draft("Second")).unwrap();
+        store
+            .patch(
+                second,
+                TicketPatch::new(None, None, Some(Status::InProgress)).unwrap(),
+                None,
+            )
+            .await
+            .unwrap();
+        store.flush().unwrap();
+        drop(store);
+
+        let mut store = TicketStore::open(LogStorage::open(dir.path()).unwrap()).unwrap();
+        let ticket = store.get(first).await.unwrap();
+        assert_eq!(ticket.title, "First");
+        assert_eq!(ticket.status, Status::ToDo);
+        let ticket = store.get(second).await.unwrap();
+        assert_eq!(ticket.title, "Second");
+        assert_eq!(ticket.status, Status::InProgress);
+        assert_eq!(ticket.version, 2);
+
+        let third = store.add_ticket(draft("Third")).unwrap();
+        assert_eq!(third, TicketId(2));
+    }
+
+    #[tokio::test]
+    async fn test_list_pages_through_matches() {
+        let mut store = TicketStore::new();
+        for title in ["Fix login", "Write docs", "Fix logout", "Fix signup"] {
+            store.add_ticket(draft(title)).unwrap();
+        }
+
+        let mut query = TicketQuery {
+            search: Some("FIX".into()),
+            limit: Some(2),
+            ..Default::default()
+        };
+        let page = store.list(&query).await;
+        let titles: Vec<_> = page.tickets.iter().map(|t| t.title.clone()).collect();
+        assert_eq!(titles, ["Fix login", "Fix logout"]);
+        assert_eq!(page.next_cursor, Some(TicketId(3)));
+
+        query.cursor = page.next_cursor;
+        let page = store.list(&query).await;
+        assert_eq!(page.tickets.len(), 1);
+        assert_eq!(page.tickets[0].title, "Fix signup");
+        assert_eq!(page.next_cursor, None);
+
+        let query = TicketQuery {
+            status: Some(Status::Done),
+            ..Default::default()
+        };
+        assert!(store.list(&query).await.tickets.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_archive_and_remove() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut store = TicketStore::open(LogStorage::open(dir.path()).unwrap()).unwrap();
+        let archived = store.add_ticket(draft("Archived")).unwrap();
+        let removed = store.add_ticket(draft("Removed")).unwrap();
+
+        store.archive(archived).unwrap();
+        assert!(store.get(archived).await.is_none());
+        assert!(store.get_including_archived(archived).await.is_some());
+        assert!(matches!(
+            store.archive(archived).unwrap_err(),
+            AppError::TicketArchived
+        ));
+        assert_eq!(store.list(&TicketQuery::default()).await.tickets.len(), 1);
+
+        let ticket = store.remove(removed).await.unwrap();
+        assert_eq!(ticket.title, "Removed");
+        assert!(store.get_including_archived(removed).await.is_none());
+        assert!(matches!(
+            store.remove(removed).await.unwrap_err(),
+            AppError::NotTicket
+        ));
+        drop(store);
+
+        let mut store = TicketStore::open(LogStorage::open(dir.path()).unwrap()).unwrap();
+        assert!(store.is_archived(archived));
+        assert!(store.get_including_archived(removed).await.is_none());
+        assert_eq!(store.add_ticket(draft("New")).unwrap(), TicketId(2));
+    }
+
+    #[tokio::test]
+    async fn test_patch_unknown_ticket() {
+        let mut store = TicketStore::new();
+        let patch = TicketPatch::new(None, None, Some(Status::Done)).unwrap();
+        let err = store.patch(TicketId(42), patch, None).await.unwrap_err();
+        assert!(matches!(err, AppError::NotTicket));
+    }
+
+    #[tokio::test]
+    async fn test_history_records_changes() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut store = TicketStore::open(LogStorage::open(dir.path()).unwrap()).unwrap();
+        let id = store.add_ticket(draft("A title")).unwrap();
+        let patch = TicketPatch::new(
+            Some("A title".try_into().unwrap()),
+            None,
+            Some(Status::InProgress),
+        )
+        .unwrap();
+        store.patch(id, patch, None).await.unwrap();
+        let patch = TicketPatch::new(Some("New title".try_into().unwrap()), None, None).unwrap();
+        store.patch(id, patch, None).await.unwrap();
+
+        let changes = |store: &TicketStore| -> Vec<TicketChange> {
+            store
+                .history(id)
+                .unwrap()
+                .iter()
+                .map(|entry| entry.change.clone())
+                .collect()
+        };
+        let expected = vec![
+            TicketChange::Created {
+                title: "A title".try_into().unwrap(),
+                description: "A description".try_into().unwrap(),
+                assignee: None,
+                tags: BTreeSet::new(),
+                priority: None,
+                due_date: None,
+            },
+            TicketChange::StatusChanged {
+                from: Status::ToDo,
+                to: Status::InProgress,
+            },
+            TicketChange::TitleChanged {
+                from: "A title".try_into().unwrap(),
+                to: "New title".try_into().unwrap(),
+            },
+        ];
+        assert_eq!(changes(&store), expected);
+        let history = store.history(id).unwrap().to_vec();
+        drop(store);
+
+        let mut store = TicketStore::open(LogStorage::open(dir.path()).unwrap()).unwrap();
+        assert_eq!(store.history(id).unwrap(), history);
+
+        store.remove(id).await.unwrap();
+        assert!(store.history(id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_patch_follows_workflow() {
+        let mut store = TicketStore::new();
+        let id = store.add_ticket(draft("A title")).unwrap();
+        let done = TicketPatch::new(None, None, Some(Status::Done)).unwrap();
+
+        let err = store.patch(id, done.clone(), None).await.unwrap_err();
+        assert!(matches!(
+            err,
+            AppError::TicketStatusError(TicketStatusError::IllegalTransition {
+                from: Status::ToDo,
+                to: Status::Done
+            })
+        ));
+        assert_eq!(store.history(id).unwrap().len(), 1);
+
+        let mut store = store.with_workflow(StatusWorkflow::unrestricted());
+        let ticket = store.patch(id, done, None).await.unwrap();
+        assert_eq!(ticket.status, Status::Done);
+    }
+
+    #[tokio::test]
+    async fn test_assign_and_filter() {
+        let mut store = TicketStore::new();
+        let alice = store.add_ticket(draft("Alice's")).unwrap();
+        let bob = store
+            .add_ticket(TicketDraft {
+                assignee: Some("Bob".try_into().unwrap()),
+                ..draft("Bob's")
+            })
+            .unwrap();
+
+        let patch = TicketPatch {
+            assignee: Some(Some("Alice".try_into().unwrap())),
+            ..Default::default()
+        };
+        store.patch(alice, patch, None).await.unwrap();
+
+        let query = TicketQuery {
+            assignee: Some("Alice".try_into().unwrap()),
+            ..Default::default()
+        };
+        let page = store.list(&query).await;
+        assert_eq!(page.tickets.len(), 1);
+        assert_eq!(page.tickets[0].id, alice);
+
+        let patch = TicketPatch {
+            assignee: Some(None),
+            ..Default::default()
+        };
+        let ticket = store.patch(bob, patch, None).await.unwrap();
+        assert_eq!(ticket.assignee, None);
+        assert_eq!(
+            store.history(bob).unwrap().last().unwrap().change,
+            TicketChange::AssigneeChanged {
+                from: Some("Bob".try_into().unwrap()),
+                to: None,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fields_follow_policy() {
+        let mut policy = ValidationPolicy::default();
+        policy.title.max_length = 10;
+        let mut store = TicketStore::new().with_policy(policy).unwrap();
+
+        let err = store
+            .add_ticket(draft("Too long for this store"))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            AppError::TicketTitleError(TicketTitleError::TooLong {
+                max: 10,
+                actual: 23
+            })
+        ));
+
+        let id = store.add_ticket(draft("  Padded  ")).unwrap();
+        let ticket = store.get(id).await.unwrap();
+        assert_eq!(ticket.title, "Padded");
+    }
+
+    #[tokio::test]
+    async fn test_reopen_with_custom_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let open = |policy| {
+            TicketStore::open(LogStorage::open(dir.path()).unwrap())
+                .unwrap()
+                .with_policy(policy)
+        };
+
+        let mut policy = ValidationPolicy::default();
+        policy.title.max_length = 20;
+        policy.title.normalization = Normalization::Collapse;
+        let mut store = open(policy).unwrap();
+        let id = store.add_ticket(draft("A   stricter  title")).unwrap();
+        drop(store);
+
+        let store = open(policy).unwrap();
+        assert_eq!(store.get(id).await.unwrap().title, "A stricter title");
+        drop(store);
+
+        // What a looser policy lets in could not be read back.
+        policy.title.max_length = 100;
+        let err = open(policy).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn test_patch_with_stale_version() {
+        let mut store = TicketStore::new();
+        let id = store.add_ticket(draft("A title")).unwrap();
+        let patch = TicketPatch::new(None, None, Some(Status::InProgress)).unwrap();
+
+        let ticket = store.patch(id, patch.clone(), Some(1)).await.unwrap();
+        assert_eq!(ticket.version, 2);
+
+        let err = store.patch(id, patch, Some(1)).await.unwrap_err();
+        assert!(matches!(
+            err,
+            AppError::VersionMismatch {
+                expected: 1,
+                actual: 2
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_patch_keeps_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = TicketStore::open(LogStorage::open(dir.path()).unwrap()).unwrap();
+        let id = store.add_ticket(draft("A title")).unwrap();
+        let mut events = store.subscribe();
+        let log = dir.path().join(LogStorage::FILE_NAME);
+        let log_len = std::fs::metadata(&log).unwrap().len();
+
+        let patch = TicketPatch::new(Some("A title".try_into().unwrap()), None, None).unwrap();
+        let ticket = store.patch(id, patch, Some(1)).await.unwrap();
+        assert_eq!(ticket.version, 1);
+        assert_eq!(store.history(id).unwrap().len(), 1);
+        // Nothing is written down, nor published.
+        assert_eq!(std::fs::metadata(&log).unwrap().len(), log_len);
+        assert!(events.try_recv().is_err());
+
+        let patch =
+            TicketPatch::new(Some("Another title".try_into().unwrap()), None, None).unwrap();
+        let ticket = store.patch(id, patch, Some(1)).await.unwrap();
+        assert_eq!(ticket.version, 2);
+    }
+
+    #[tokio::test]
+    async fn test_tags_are_indexed() {
+        let tags = |tags: &[&str]| -> BTreeSet<TicketTag> {
+            tags.iter().map(|tag| (*tag).try_into().unwrap()).collect()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = TicketStore::open(LogStorage::open(dir.path()).unwrap()).unwrap();
+        let login = store
+            .add_ticket(TicketDraft {
+                tags: tags(&["backend", "bug"]),
+                ..draft("Fix login")
+            })
+            .unwrap();
+        let docs = store
+            .add_ticket(TicketDraft {
+                tags: tags(&["docs"]),
+                ..draft("Write docs")
+            })
+            .unwrap();
+
+        let patch = TicketPatch {
+            add_tags: tags(&["backend", "docs"]),
+            remove_tags: tags(&["bug"]),
+            ..Default::default()
+        };
+        let ticket = store.patch(login, patch, None).await.unwrap();
+        assert_eq!(ticket.tags, tags(&["backend", "docs"]));
+        assert_eq!(
+            store.history(login).unwrap().last().unwrap().change,
+            TicketChange::TagsChanged {
+                added: tags(&["docs"]),
+                removed: tags(&["bug"]),
+            }
+        );
+        drop(store);
+
+        let store = TicketStore::open(LogStorage::open(dir.path()).unwrap()).unwrap();
+        let ids = |tag_match, wanted: &[&str]| {
+            let query = TicketQuery {
+                tags: tags(wanted),
+                tag_match,
+                ..Default::default()
+            };
+            let store = &store;
+            async move {
+                let page = store.list(&query).await;
+                page.tickets.iter().map(|t| t.id).collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(ids(TagMatch::All, &["docs"]).await, [login, docs]);
+        assert_eq!(ids(TagMatch::All, &["backend", "docs"]).await, [login]);
+        assert_eq!(ids(TagMatch::Any, &["bug", "docs"]).await, [login, docs]);
+        assert!(ids(TagMatch::All, &["bug"]).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_patch_cannot_add_and_remove_a_tag() {
+        let mut store = TicketStore::new();
+        let id = store.add_ticket(draft("A title")).unwrap();
+        let tag: TicketTag = "backend".try_into().unwrap();
+        let patch = TicketPatch {
+            add_tags: BTreeSet::from([tag.clone()]),
+            remove_tags: BTreeSet::from([tag]),
+            ..Default::default()
+        };
+
+        let err = store.patch(id, patch, None).await.unwrap_err();
+        assert!(matches!(
+            err,
+            AppError::TicketPatchError(TicketPatchError::TagAddedAndRemoved(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sort_and_overdue() {
+        let date = |day| NaiveDate::from_ymd_opt(2024, 5, day).unwrap();
+        let clock = Arc::new(FixedClock::new(
+            date(10).and_hms_opt(9, 0, 0).unwrap().and_utc(),
+        ));
+        let mut store = TicketStore::new().with_clock(clock.clone());
+
+        let mut add = |title, priority, due_date| {
+            store
+                .add_ticket(TicketDraft {
+                    priority,
+                    due_date,
+                    ..draft(title)
+                })
+                .unwrap()
+        };
+        let low = add("Low", Some(Priority::Low), Some(date(12)));
+        let none = add("None", None, None);
+        let critical = add("Critical", Some(Priority::Critical), Some(date(8)));
+        let high = add("High", Some(Priority::High), Some(date(11)));
+
+        let ids = |page: &TicketPage| page.tickets.iter().map(|t| t.id).collect::<Vec<_>>();
+        let mut query = TicketQuery {
+            sort: TicketSort::Priority,
+            limit: Some(3),
+            ..Default::default()
+        };
+        let page = store.list(&query).await;
+        assert_eq!(ids(&page), [critical, high, low]);
+        assert_eq!(page.next_cursor, Some(none));
+        query.cursor = page.next_cursor;
+        assert_eq!(ids(&store.list(&query).await), [none]);
+
+        let query = TicketQuery {
+            sort: TicketSort::DueDate,
+            ..Default::default()
+        };
+        assert_eq!(ids(&store.list(&query).await), [critical, high, low, none]);
+
+        let query = TicketQuery {
+            overdue: true,
+            ..Default::default()
+        };
+        assert_eq!(ids(&store.list(&query).await), [critical]);
+
+        clock.advance(Duration::days(2));
+        assert_eq!(ids(&store.list(&query).await), [critical, high]);
+
+        let patch = TicketPatch {
+            due_date: Some(None),
+            ..Default::default()
+        };
+        store.patch(critical, patch, None).await.unwrap();
+        assert_eq!(ids(&store.list(&query).await), [high]);
+        let entry = store.history(critical).unwrap().last().unwrap();
+        assert_eq!(entry.at, clock.now());
+        assert_eq!(
+            entry.change,
+            TicketChange::DueDateChanged {
+                from: Some(date(8)),
+                to: None,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_comments() {
+        let comment = |body: &str| CommentDraft {
+            author: "Alice".try_into().unwrap(),
+            body: body.try_into().unwrap(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = TicketStore::open(LogStorage::open(dir.path()).unwrap()).unwrap();
+        let first = store.add_ticket(draft("First")).unwrap();
+        let second = store.add_ticket(draft("Second")).unwrap();
+
+        for body in ["One", "Two", "Three"] {
+            store.add_comment(first, comment(body)).unwrap();
+        }
+        store.add_comment(second, comment("Elsewhere")).unwrap();
+        drop(store);
+
+        let mut store = TicketStore::open(LogStorage::open(dir.path()).unwrap()).unwrap();
+        let mut query = CommentQuery {
+            cursor: None,
+            limit: Some(2),
+        };
+        let page = store.comments(first, &query).unwrap();
+        let bodies: Vec<_> = page.comments.iter().map(|c| c.body.as_str()).collect();
+        assert_eq!(bodies, ["One", "Two"]);
+
+        query.cursor = page.next_cursor;
+        let page = store.comments(first, &query).unwrap();
+        assert_eq!(page.comments.len(), 1);
+        assert_eq!(page.comments[0].body.as_str(), "Three");
+        assert_eq!(page.next_cursor, None);
+
+        let new = store.add_comment(second, comment("Another")).unwrap();
+        assert_eq!(new.id, CommentId(4));
+
+        store.archive(first).unwrap();
+        let err = store.add_comment(first, comment("Too late")).unwrap_err();
+        assert!(matches!(err, AppError::NotTicket));
+        assert_eq!(store.comments(first, &query).unwrap().comments.len(), 1);
+
+        store.remove(first).await.unwrap();
+        assert!(store.comments(first, &CommentQuery::default()).is_none());
+        drop(store);
+
+        let store = TicketStore::open(LogStorage::open(dir.path()).unwrap()).unwrap();
+        assert!(store.comments(first, &CommentQuery::default()).is_none());
+        let page = store.comments(second, &CommentQuery::default()).unwrap();
+        assert_eq!(page.comments.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_blockers_keep_tickets_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = TicketStore::open(LogStorage::open(dir.path()).unwrap())
+            .unwrap()
+            .with_workflow(StatusWorkflow::unrestricted());
+        let blocker = store.add_ticket(draft("Blocker")).unwrap();
+        let blocked = store.add_ticket(draft("Blocked")).unwrap();
+        let blocks = Link {
+            source: blocker,
+            kind: LinkKind::Blocks,
+            target: blocked,
+        };
+        store.link(blocks).unwrap();
+
+        let err = store
+            .link(Link {
+                source: blocked,
+                kind: LinkKind::Blocks,
+                target: blocker,
+            })
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            AppError::TicketLinkError(TicketLinkError::Cycle)
+        ));
+        drop(store);
+
+        let mut store = TicketStore::open(LogStorage::open(dir.path()).unwrap())
+            .unwrap()
+            .with_workflow(StatusWorkflow::unrestricted());
+        assert_eq!(store.links(blocked).unwrap(), [blocks]);
+
+        let done = TicketPatch::new(None, None, Some(Status::Done)).unwrap();
+        let err = store.patch(blocked, done.clone(), None).await.unwrap_err();
+        assert!(matches!(err, AppError::TicketBlocked(ref blockers) if blockers == &[blocker]));
+
+        store.patch(blocker, done.clone(), None).await.unwrap();
+        store.patch(blocked, done, None).await.unwrap();
+
+        store.unlink(blocks).unwrap();
+        assert!(matches!(store.unlink(blocks), Err(AppError::NotLink)));
+        assert!(store.links(blocked).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_removing_a_ticket_drops_its_links() {
+        let mut store = TicketStore::new();
+        let parent = store.add_ticket(draft("Parent")).unwrap();
+        let child = store.add_ticket(draft("Child")).unwrap();
+        store
+            .link(Link {
+                source: parent,
+                kind: LinkKind::Parent,
+                target: child,
+            })
+            .unwrap();
+
+        store.remove(parent).await.unwrap();
+        assert!(store.links(child).unwrap().is_empty());
+        assert!(store.links(parent).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_search_follows_changes() {
+        let mut store = TicketStore::new();
+        let login = store.add_ticket(draft("Fix login")).unwrap();
+        let docs = store.add_ticket(draft("Write docs")).unwrap();
+
+        let ids = |hits: Vec<SearchHit>| hits.iter().map(|hit| hit.ticket.id).collect::<Vec<_>>();
+        let query = |text: &str| SearchQuery {
+            text: text.into(),
+            ..Default::default()
+        };
+        assert_eq!(ids(store.search(&query("login docs")).await), [login, docs]);
+
+        let patch = TicketPatch::new(Some("Fix logout".try_into().unwrap()), None, None).unwrap();
+        store.patch(login, patch, None).await.unwrap();
+        assert!(store.search(&query("login")).await.is_empty());
+        assert_eq!(ids(store.search(&query("LOGOUT")).await), [login]);
+
+        store.archive(docs).unwrap();
+        assert!(store.search(&query("docs")).await.is_empty());
+        let archived = SearchQuery {
+            include_archived: true,
+            ..query("docs")
+        };
+        assert_eq!(ids(store.search(&archived).await), [docs]);
+
+        store.remove(docs).await.unwrap();
+        assert!(store.search(&archived).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_atomic_batches() {
+        let mut store = TicketStore::new();
+        let bad_title = || Err(AppError::from(TicketTitleError::Empty));
+
+        let results = store.add_tickets(vec![Ok(draft("First")), bad_title()], true);
+        assert!(matches!(results[0], Err(AppError::BatchRejected)));
+        assert!(matches!(results[1], Err(AppError::TicketTitleError(_))));
+
+        let results = store.add_tickets(vec![Ok(draft("First")), bad_title()], false);
+        assert_eq!(results[0].as_ref().unwrap(), &TicketId(0));
+        assert!(results[1].is_err());
+
+        let status = |status| BulkPatch {
+            id: TicketId(0),
+            patch: TicketPatch::new(None, None, Some(status)).unwrap(),
+            version: None,
+        };
+        // ToDo -> Done is only legal through InProgress, which the batch does first.
+        let results = store
+            .patch_tickets(
+                vec![Ok(status(Status::InProgress)), Ok(status(Status::Done))],
+                true,
+            )
+            .await;
+        assert_eq!(results[1].as_ref().unwrap().status, Status::Done);
+
+        let results = store
+            .patch_tickets(
+                vec![Ok(status(Status::ToDo)), Ok(status(Status::Done))],
+                true,
+            )
+            .await;
+        assert!(matches!(results[0], Err(AppError::BatchRejected)));
+        assert!(matches!(results[1], Err(AppError::TicketStatusError(_))));
+        let ticket = store.get(TicketId(0)).await.unwrap();
+        assert_eq!(ticket.status, Status::Done);
+        assert_eq!(ticket.version, 3);
+    }
+
+    #[tokio::test]
+    async fn test_atomic_batch_resolves_its_own_blockers() {
+        let mut store = TicketStore::new().with_workflow(StatusWorkflow::unrestricted());
+        let blocker = store.add_ticket(draft("Blocker")).unwrap();
+        let blocked = store.add_ticket(draft("Blocked")).unwrap();
+        store
+            .link(Link {
+                source: blocker,
+                kind: LinkKind::Blocks,
+                target: blocked,
+            })
+            .unwrap();
+
+        let done = |id| {
+            Ok(BulkPatch {
+                id,
+                patch: TicketPatch::new(None, None, Some(Status::Done)).unwrap(),
+                version: None,
+            })
+        };
+        let results = store
+            .patch_tickets(vec![done(blocked), done(blocker)], true)
+            .await;
+        assert!(matches!(results[0], Err(AppError::TicketBlocked(_))));
+        assert!(matches!(results[1], Err(AppError::BatchRejected)));
+
+        let results = store
+            .patch_tickets(vec![done(blocker), done(blocked)], true)
+            .await;
+        assert_eq!(results[0].as_ref().unwrap().status, Status::Done);
+        assert_eq!(results[1].as_ref().unwrap().status, Status::Done);
+    }
+
+    #[tokio::test]
+    async fn test_export_and_import() {
+        let mut store = TicketStore::new();
+        store.add_ticket(draft("First")).unwrap();
+        let second = store.add_ticket(draft("Second")).unwrap();
+        store.archive(second).unwrap();
+
+        let mut exported = Vec::new();
+        let count = store
+            .export(TransferFormat::JsonLines, &mut exported)
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut imported = TicketStore::open(LogStorage::open(dir.path()).unwrap()).unwrap();
+        let count = imported
+            .import(TransferFormat::JsonLines, exported.as_slice())
+            .unwrap();
+        assert_eq!(count, 2);
+        assert!(imported.is_archived(second));
+        assert_eq!(imported.add_ticket(draft("Third")).unwrap(), TicketId(2));
+
+        // Ids that are already taken reject the whole import.
+        let error = imported
+            .import(TransferFormat::JsonLines, exported.as_slice())
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        imported.flush().unwrap();
+        drop(imported);
+
+        let reopened = TicketStore::open(LogStorage::open(dir.path()).unwrap()).unwrap();
+        let ticket = reopened.get(TicketId(0)).await.unwrap();
+        assert_eq!(ticket.title, "First");
+        assert!(reopened.is_archived(second));
+        assert!(reopened.get(TicketId(2)).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_changes_are_published() {
+        let mut store = TicketStore::new();
+        let mut events = store.subscribe();
+
+        let id = store.add_ticket(draft("First")).unwrap();
+        let patch = TicketPatch::new(None, None, Some(Status::InProgress)).unwrap();
+        store.patch(id, patch, None).await.unwrap();
+        // Failed patches publish nothing.
+        let patch = TicketPatch::new(None, None, Some(Status::ToDo)).unwrap();
+        store.patch(id, patch, Some(1)).await.unwrap_err();
+
+        let event = events.try_recv().unwrap();
+        assert!(matches!(event, TicketEvent::Created { .. }));
+        assert_eq!(event.ticket().status, Status::ToDo);
+        let event = events.try_recv().unwrap();
+        assert!(matches!(event, TicketEvent::Patched { .. }));
+        assert_eq!(event.ticket().status, Status::InProgress);
+        assert_eq!(event.ticket().version, 2);
+        assert!(events.try_recv().is_err());
+
+        // Writers don't wait for slow subscribers, which miss the oldest events instead.
+        for i in 0..feed::FEED_CAPACITY + 2 {
+            store.add_ticket(draft(&format!("Ticket {i}"))).unwrap();
+        }
+        assert!(matches!(
+            events.try_recv(),
+            Err(broadcast::error::TryRecvError::Lagged(2))
+        ));
+        assert_eq!(events.try_recv().unwrap().ticket().id, TicketId(3));
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::data::{Priority, Status, Ticket};
+use crate::store::TicketId;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferFormat {
+    // One JSON ticket per line.
+    JsonLines,
+    // One ticket per row, after a header row. Tags are separated by spaces,
+    // which is fine since tags cannot contain whitespace.
+    Csv,
+}
+
+impl TryFrom<&str> for TransferFormat {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "jsonl" | "json_lines" => Ok(Self::JsonLines),
+            "csv" => Ok(Self::Csv),
+            _ => Err(()),
+        }
+    }
+}
+
+// A ticket as it is exported, together with the store state that isn't part of it.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ExportedTicket {
+    #[serde(flatten)]
+    pub ticket: Ticket,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub archived: bool,
+}
+
+const CSV_HEADER: [&str; 11] = [
+    "id",
+    "title",
+    "description",
+    "status",
+    "assignee",
+    "tags",
+    "priority",
+    "due_date",
+    "version",
+    "created_by",
+    "archived",
+];
+
+pub fn write_tickets<'a>(
+    format: TransferFormat,
+    tickets: impl IntoIterator<Item = &'a ExportedTicket>,
+    mut writer: impl Write,
+) -> io::Result<usize> {
+    let mut count = 0;
+    match format {
+        TransferFormat::JsonLines => {
+            for ticket in tickets {
+                serde_json::to_writer(&mut writer, ticket)?;
+                writer.write_all(b"\n")?;
+                count += 1;
+            }
+            writer.flush()?;
+        }
+        TransferFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(writer);
+            writer.write_record(CSV_HEADER)?;
+            for ticket in tickets {
+                writer.write_record(csv_record(ticket)?)?;
+                count += 1;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(count)
+}
+
+// Field values go through the same validation as in requests.
+// Errors name the line or row they were found on.
+pub fn read_tickets(
+    format: TransferFormat,
+    reader: impl BufRead,
+) -> io::Result<Vec<ExportedTicket>> {
+    match format {
+        TransferFormat::JsonLines => reader
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+            .map(|(index, line)| {
+                serde_json::from_str(&line?).map_err(|e| invalid_data(index + 1, e))
+            })
+            .collect(),
+        TransferFormat::Csv => {
+            let mut reader = csv::Reader::from_reader(reader);
+            let headers = reader.headers()?.clone();
+            reader
+                .records()
+                .enumerate()
+                .map(|(index, record)| {
+                    // Row 1 is the header.
+                    let row = index + 2;
+                    from_csv(&headers, &record?).map_err(|e| invalid_data(row, e))
+                })
+                .collect()
+        }
+    }
+}
+
+fn csv_record(exported: &ExportedTicket) -> io::Result<[String; 11]> {
+    let ticket = &exported.ticket;
+    Ok([
+        ticket.id.0.to_string(),
+        ticket.title.as_str().to_owned(),
+        ticket.description.as_str().to_owned(),
+        to_plain(&ticket.status)?,
+        ticket
+            .assignee
+            .as_ref()
+            .map(|assignee| assignee.as_str().to_owned())
+            .unwrap_or_default(),
+        ticket
+            .tags
+            .iter()
+            .map(|tag| tag.as_str())
+            .collect::<Vec<_>>()
+            .join(" "),
+        ticket
+            .priority
+            .as_ref()
+            .map(to_plain)
+            .transpose()?
+            .unwrap_or_default(),
+        ticket
+            .due_date
+            .map(|due_date| due_date.to_string())
+            .unwrap_or_default(),
+        ticket.version.to_string(),
+        ticket
+            .created_by
+            .as_ref()
+            .map(|created_by| created_by.as_str().to_owned())
+            .unwrap_or_default(),
+        exported.archived.to_string(),
+    ])
+}
+
+fn from_csv(
+    headers: &csv::StringRecord,
+    record: &csv::StringRecord,
+) -> Result<ExportedTicket, String> {
+    // Columns are matched by name, missing ones are treated as empty.
+    let field = |name: &str| {
+        headers
+            .iter()
+            .position(|header| header == name)
+            .and_then(|i| record.get(i))
+            .unwrap_or_default()
+    };
+    let optional = |name: &str| Some(field(name)).filter(|value| !value.is_empty());
+    let error = |name: &str, e: &dyn std::fmt::Display| format!("{name}: {e}");
+
+    let ticket = Ticket {
+        id: TicketId(field("id").parse().map_err(|e| error("id", &e))?),
+        title: field("title").try_into().map_err(|e| error("title", &e))?,
+        description: field("description")
+            .try_into()
+            .map_err(|e| error("description", &e))?,
+        status: Status::try_from(field("status")).map_err(|e| error("status", &e))?,
+        assignee: optional("assignee")
+            .map(TryInto::try_into)
+            .transpose()
+            .map_err(|e| error("assignee", &e))?,
+        tags: field("tags")
+            .split_whitespace()
+            .map(TryInto::try_into)
+            .collect::<Result<BTreeSet<_>, _>>()
+            .map_err(|e| error("tags", &e))?,
+        priority: optional("priority")
+            .map(serde_plain::<Priority>)
+            .transpose()
+            .map_err(|e| error("priority", &e))?,
+        due_date: optional("due_date")
+            .map(str::parse::<NaiveDate>)
+            .transpose()
+            .map_err(|e| error("due_date", &e))?,
+        created_by: optional("created_by")
+            .map(TryInto::try_into)
+            .transpose()
+            .map_err(|e| error("created_by", &e))?,
+        version: optional("version")
+            .map(str::parse)
+            .transpose()
+            .map_err(|e| error("version", &e))?
+            .unwrap_or_else(Ticket::initial_version),
+    };
+    let archived = optional("archived")
+        .map(str::parse)
+        .transpose()
+        .map_err(|e| error("archived", &e))?
+        .unwrap_or_default();
+
+    Ok(ExportedTicket { ticket, archived })
+}
+
+// Unit enum variants, such as `Status::InProgress`, are written by their serde name.
+fn to_plain<T: Serialize>(value: &T) -> io::Result<String> {
+    match serde_json::to_value(value)? {
+        serde_json::Value::String(value) => Ok(value),
+        value => Ok(value.to_string()),
+    }
+}
+
+fn serde_plain<T: for<'de> Deserialize<'de>>(value: &str) -> Result<T, serde_json::Error> {
+    serde_json::from_value(serde_json::Value::String(value.to_owned()))
+}
+
+fn invalid_data(line: usize, error: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {line}: {error}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exported() -> ExportedTicket {
+        ExportedTicket {
+            ticket: Ticket {
+                id: TicketId(7),
+                title: "Fix, \"quickly\"".try_into().unwrap(),
+                description: "First line\nsecond line, with a comma".try_into().unwrap(),
+                status: Status::InProgress,
+                assignee: Some("alice".try_into().unwrap()),
+                tags: ["backend".try_into().unwrap(), "urgent".try_into().unwrap()].into(),
+                priority: Some(Priority::High),
+                due_date: NaiveDate::from_ymd_opt(2024, 5, 1),
+                created_by: Some("bob".try_into().unwrap()),
+                version: 3,
+            },
+            archived: true,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for format in [TransferFormat::JsonLines, TransferFormat::Csv] {
+            let mut buffer = Vec::new();
+            write_tickets(format, [&exported()], &mut buffer).unwrap();
+            let tickets = read_tickets(format, buffer.as_slice()).unwrap();
+            assert_eq!(tickets, vec![exported()], "{format:?}");
+        }
+    }
+
+    #[test]
+    fn test_invalid_rows_are_reported() {
+        let csv =
+            "id,title,description,status\n0,A title,A description,todo\n1,,A description,todo\n";
+        let error = read_tickets(TransferFormat::Csv, csv.as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().starts_with("line 3: title"), "{error}");
+    }
+}
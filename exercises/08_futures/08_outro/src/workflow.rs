@@ -0,0 +1,109 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::data::Status;
+use crate::error::TicketStatusError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct Transition {
+    pub from: Status,
+    pub to: Status,
+}
+
+// The set of status transitions a patch is allowed to perform.
+// Leaving the status unchanged is always allowed.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StatusWorkflow {
+    allowed: BTreeSet<Transition>,
+}
+
+impl StatusWorkflow {
+    pub fn new(allowed: impl IntoIterator<Item = Transition>) -> Self {
+        Self {
+            allowed: allowed.into_iter().collect(),
+        }
+    }
+
+    // Any status can move to any other status.
+    pub fn unrestricted() -> Self {
+        let statuses = [Status::ToDo, Status::InProgress, Status::Done];
+        Self::new(
+            statuses
+                .into_iter()
+                .flat_map(|from| statuses.into_iter().map(move |to| Transition { from, to })),
+        )
+    }
+
+    pub fn allow(mut self, from: Status, to: Status) -> Self {
+        self.allowed.insert(Transition { from, to });
+        self
+    }
+
+    pub fn is_allowed(&self, from: Status, to: Status) -> bool {
+        from == to || self.allowed.contains(&Transition { from, to })
+    }
+
+    pub fn check(&self, from: Status, to: Status) -> Result<(), TicketStatusError> {
+        if self.is_allowed(from, to) {
+            Ok(())
+        } else {
+            Err(TicketStatusError::IllegalTransition { from, to })
+        }
+    }
+}
+
+// Work has to be started before it can be finished,
+// and finished work can only be reopened.
+impl Default for StatusWorkflow {
+    fn default() -> Self {
+        Self::new([
+            Transition {
+                from: Status::ToDo,
+                to: Status::InProgress,
+            },
+            Transition {
+                from: Status::InProgress,
+                to: Status::ToDo,
+            },
+            Transition {
+                from: Status::InProgress,
+                to: Status::Done,
+            },
+            Transition {
+                from: Status::Done,
+                to: Status::ToDo,
+            },
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_workflow() {
+        let workflow = StatusWorkflow::default();
+        assert!(workflow.is_allowed(Status::ToDo, Status::InProgress));
+        assert!(workflow.is_allowed(Status::Done, Status::ToDo));
+        assert!(workflow.is_allowed(Status::Done, Status::Done));
+        assert!(!workflow.is_allowed(Status::ToDo, Status::Done));
+        assert!(!workflow.is_allowed(Status::Done, Status::InProgress));
+
+        let err = workflow.check(Status::ToDo, Status::Done).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Illegal status transition from ToDo to Done"
+        );
+    }
+
+    #[test]
+    fn test_configured_workflow() {
+        let workflow = StatusWorkflow::default().allow(Status::ToDo, Status::Done);
+        assert!(workflow.is_allowed(Status::ToDo, Status::Done));
+
+        let workflow = StatusWorkflow::unrestricted();
+        assert!(workflow.is_allowed(Status::Done, Status::InProgress));
+    }
+}
@@ -13,3 +13,15 @@ pub fn valid_title() -> String {
 pub fn valid_description() -> String {
     "A description".into()
 }
+
+pub fn valid_assignee() -> String {
+    "Alice".into()
+}
+
+pub fn valid_tag() -> String {
+    "backend".into()
+}
+
+pub fn valid_comment() -> String {
+    "A comment".into()
+}
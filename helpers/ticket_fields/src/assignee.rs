@@ -0,0 +1,30 @@
+use crate::policy::ValidationPolicy;
+use crate::text::{self, text_field};
+
+text_field! {
+    /// The person a ticket is assigned to.
+    pub struct TicketAssignee;
+    pub enum TicketAssigneeError for "assignee"
+}
+
+impl TicketAssignee {
+    pub fn new(assignee: &str, policy: &ValidationPolicy) -> Result<Self, TicketAssigneeError> {
+        let assignee = text::apply(assignee, &policy.assignee)?;
+        Ok(Self(assignee.into_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::overly_long_title;
+
+    #[test]
+    fn test_try_from_long_string() {
+        let err = TicketAssignee::try_from(overly_long_title()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The assignee cannot be longer than 50 characters, but it has 84"
+        );
+    }
+}
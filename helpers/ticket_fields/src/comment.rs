@@ -0,0 +1,35 @@
+use crate::policy::ValidationPolicy;
+use crate::text::{self, text_field};
+
+text_field! {
+    /// The text of a comment left on a ticket.
+    pub struct CommentBody;
+    pub enum CommentBodyError for "comment"
+}
+
+impl CommentBody {
+    pub fn new(comment: &str, policy: &ValidationPolicy) -> Result<Self, CommentBodyError> {
+        let comment = text::apply(comment, &policy.comment)?;
+        Ok(Self(comment.into_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_from_long_string() {
+        let err = CommentBody::try_from("a".repeat(2001)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The comment cannot be longer than 2000 characters, but it has 2001"
+        );
+    }
+
+    #[test]
+    fn test_line_breaks_are_allowed() {
+        let comment = CommentBody::try_from("First line\n\tSecond line").unwrap();
+        assert_eq!(comment.0, "First line\n\tSecond line");
+    }
+}
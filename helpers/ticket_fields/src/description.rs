@@ -1,39 +1,19 @@
-#[derive(Debug, PartialEq, Clone, Eq)]
-pub struct TicketDescription(String);
+use crate::policy::ValidationPolicy;
+use crate::text::{self, text_field};
 
-#[derive(Debug, thiserror::Error)]
-pub enum TicketDescriptionError {
-    #[error("The description cannot be empty")]
-    Empty,
-    #[error("The description cannot be longer than 500 characters")]
-    TooLong,
+text_field! {
+    /// The details of a ticket, which may span several lines.
+    pub struct TicketDescription;
+    pub enum TicketDescriptionError for "description"
 }
 
-impl TryFrom<String> for TicketDescription {
-    type Error = TicketDescriptionError;
-
-    fn try_from(value: String) -> Result<Self, Self::Error> {
-        validate(&value)?;
-        Ok(Self(value))
-    }
-}
-
-impl TryFrom<&str> for TicketDescription {
-    type Error = TicketDescriptionError;
-
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        validate(value)?;
-        Ok(Self(value.to_string()))
-    }
-}
-
-fn validate(description: &str) -> Result<(), TicketDescriptionError> {
-    if description.is_empty() {
-        Err(TicketDescriptionError::Empty)
-    } else if description.len() > 500 {
-        Err(TicketDescriptionError::TooLong)
-    } else {
-        Ok(())
+impl TicketDescription {
+    pub fn new(
+        description: &str,
+        policy: &ValidationPolicy,
+    ) -> Result<Self, TicketDescriptionError> {
+        let description = text::apply(description, &policy.description)?;
+        Ok(Self(description.into_owned()))
     }
 }
 
@@ -41,7 +21,6 @@ fn validate(description: &str) -> Result<(), TicketDescriptionError> {
 mod tests {
     use super::*;
     use common::{overly_long_description, valid_description};
-    use std::convert::TryFrom;
 
     #[test]
     fn test_try_from_string() {
@@ -61,13 +40,27 @@ mod tests {
         let err = TicketDescription::try_from(overly_long_description()).unwrap_err();
         assert_eq!(
             err.to_string(),
-            "The description cannot be longer than 500 characters"
+            "The description cannot be longer than 500 characters, but it has 844"
         );
     }
 
+    #[test]
+    fn test_length_is_counted_in_graphemes() {
+        // A family emoji is several chars joined into one grapheme.
+        let input = "👩‍👩‍👧".repeat(500);
+        let description = TicketDescription::try_from(input.as_str()).unwrap();
+        assert_eq!(description.0, input);
+    }
+
     #[test]
     fn test_try_from_str() {
         let description = TicketDescription::try_from("A description").unwrap();
         assert_eq!(description.0, "A description");
     }
+
+    #[test]
+    fn test_line_breaks_are_allowed() {
+        let description = TicketDescription::try_from("First line\n\tSecond line").unwrap();
+        assert_eq!(description.0, "First line\n\tSecond line");
+    }
 }
@@ -0,0 +1,76 @@
+use crate::{
+    CommentBodyError, TicketAssigneeError, TicketDescriptionError, TicketTagError, TicketTitleError,
+};
+
+/// The typed error of whichever field failed to validate.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum FieldError {
+    #[error(transparent)]
+    Title(#[from] TicketTitleError),
+    #[error(transparent)]
+    Description(#[from] TicketDescriptionError),
+    #[error(transparent)]
+    Assignee(#[from] TicketAssigneeError),
+    #[error(transparent)]
+    Tag(#[from] TicketTagError),
+    #[error(transparent)]
+    Comment(#[from] CommentBodyError),
+}
+
+impl FieldError {
+    /// Recovers the error of the field that made a deserialization fail
+    /// from the message of the deserializer's error.
+    ///
+    /// serde only carries a message out of a failed deserialization, and the field
+    /// types use their error's `Display` output as that message. Returns `None`
+    /// if the deserialization failed for any other reason, e.g. a missing field.
+    pub fn from_message(message: &str) -> Option<Self> {
+        TicketTitleError::from_message(message)
+            .map(Self::from)
+            .or_else(|| TicketDescriptionError::from_message(message).map(Self::from))
+            .or_else(|| TicketAssigneeError::from_message(message).map(Self::from))
+            .or_else(|| TicketTagError::from_message(message).map(Self::from))
+            .or_else(|| CommentBodyError::from_message(message).map(Self::from))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TicketTag, TicketTitle};
+
+    #[derive(Debug, serde::Deserialize)]
+    #[allow(dead_code)]
+    struct Draft {
+        title: TicketTitle,
+        tags: Vec<TicketTag>,
+    }
+
+    fn field_error(json: &str) -> Option<FieldError> {
+        let error = serde_json::from_str::<Draft>(json).unwrap_err();
+        FieldError::from_message(&error.to_string())
+    }
+
+    #[test]
+    fn test_from_message() {
+        assert_eq!(
+            field_error(r#"{ "title": "A title", "tags": ["a tag"] }"#),
+            Some(FieldError::Tag(TicketTagError::Whitespace))
+        );
+        assert_eq!(
+            field_error(r#"{ "title": "", "tags": [] }"#),
+            Some(FieldError::Title(TicketTitleError::Empty))
+        );
+        assert_eq!(
+            field_error(&format!(
+                r#"{{ "title": "{}", "tags": [] }}"#,
+                "a".repeat(51)
+            )),
+            Some(FieldError::Title(TicketTitleError::TooLong {
+                max: 50,
+                actual: 51
+            }))
+        );
+        assert_eq!(field_error(r#"{ "title": "A title" }"#), None);
+    }
+}
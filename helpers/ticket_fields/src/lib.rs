@@ -1,6 +1,26 @@
+//! Validated text fields for tickets.
+//!
+//! Every field type checks its input against a [`ValidationPolicy`] when it is built.
+//! With the `serde` feature, the field types serialize as plain strings and deserialize
+//! through `TryFrom<String>`, so invalid input is rejected while it is parsed.
+//! [`FieldError::from_message`] recovers the typed error of the field that was rejected.
+
+mod assignee;
+mod comment;
 mod description;
+#[cfg(feature = "serde")]
+mod field_error;
+mod policy;
+mod tag;
 pub mod test_helpers;
+mod text;
 mod title;
 
-pub use description::TicketDescription;
-pub use title::TicketTitle;
+pub use assignee::{TicketAssignee, TicketAssigneeError};
+pub use comment::{CommentBody, CommentBodyError};
+pub use description::{TicketDescription, TicketDescriptionError};
+#[cfg(feature = "serde")]
+pub use field_error::FieldError;
+pub use policy::{AllowedCharacters, FieldPolicy, Normalization, ValidationPolicy};
+pub use tag::{TicketTag, TicketTagError};
+pub use title::{TicketTitle, TicketTitleError};
@@ -0,0 +1,178 @@
+/// How the raw input of a field is cleaned up before it is validated and stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Normalization {
+    /// Keep the input exactly as it is.
+    None,
+    /// Strip leading and trailing whitespace.
+    Trim,
+    /// Strip leading and trailing whitespace,
+    /// and turn every inner run of whitespace into a single space.
+    Collapse,
+}
+
+impl Normalization {
+    /// Whether `self` cleans up at least as much as `outer`:
+    /// `Collapse` does everything `Trim` does, which does more than `None`.
+    pub fn is_within(self, outer: Normalization) -> bool {
+        fn rank(normalization: Normalization) -> u8 {
+            match normalization {
+                Normalization::None => 0,
+                Normalization::Trim => 1,
+                Normalization::Collapse => 2,
+            }
+        }
+        rank(self) >= rank(outer)
+    }
+}
+
+/// Which control characters a field accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum AllowedCharacters {
+    /// No control characters at all.
+    SingleLine,
+    /// Line breaks and tabs, but no other control characters.
+    MultiLine,
+}
+
+/// The rules a single field is validated against.
+/// Lengths are counted in grapheme clusters, after normalization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub allowed: AllowedCharacters,
+    pub normalization: Normalization,
+}
+
+/// The rules every ticket field is constructed against.
+///
+/// The `TryFrom` implementations of the field types use [`ValidationPolicy::DEFAULT`];
+/// use the `new` constructors to validate against a different policy.
+///
+/// When deserialized, the fields that are left out keep their default policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct ValidationPolicy {
+    pub title: FieldPolicy,
+    pub description: FieldPolicy,
+    pub assignee: FieldPolicy,
+    pub tag: FieldPolicy,
+    pub comment: FieldPolicy,
+}
+
+impl ValidationPolicy {
+    pub const DEFAULT: Self = Self {
+        title: FieldPolicy {
+            min_length: 1,
+            max_length: 50,
+            allowed: AllowedCharacters::SingleLine,
+            normalization: Normalization::Trim,
+        },
+        description: FieldPolicy {
+            min_length: 1,
+            max_length: 500,
+            allowed: AllowedCharacters::MultiLine,
+            normalization: Normalization::Trim,
+        },
+        assignee: FieldPolicy {
+            min_length: 1,
+            max_length: 50,
+            allowed: AllowedCharacters::SingleLine,
+            normalization: Normalization::Trim,
+        },
+        tag: FieldPolicy {
+            min_length: 1,
+            max_length: 30,
+            allowed: AllowedCharacters::SingleLine,
+            normalization: Normalization::Trim,
+        },
+        comment: FieldPolicy {
+            min_length: 1,
+            max_length: 2000,
+            allowed: AllowedCharacters::MultiLine,
+            normalization: Normalization::Trim,
+        },
+    };
+}
+
+impl FieldPolicy {
+    /// Whether every value accepted by `self` is also accepted by `outer`,
+    /// and `self` cleans it up at least as much as `outer` does.
+    pub fn is_within(&self, outer: &FieldPolicy) -> bool {
+        let allowed = !matches!(
+            (self.allowed, outer.allowed),
+            (AllowedCharacters::MultiLine, AllowedCharacters::SingleLine)
+        );
+        allowed
+            && self.normalization.is_within(outer.normalization)
+            && self.min_length >= outer.min_length
+            && self.max_length <= outer.max_length
+    }
+}
+
+impl ValidationPolicy {
+    /// Whether every field of `self` is at least as strict as in `outer`.
+    ///
+    /// Deserialized fields are checked against [`ValidationPolicy::DEFAULT`],
+    /// so a policy applied on top of deserialization must be within it.
+    pub fn is_within(&self, outer: &ValidationPolicy) -> bool {
+        self.title.is_within(&outer.title)
+            && self.description.is_within(&outer.description)
+            && self.assignee.is_within(&outer.assignee)
+            && self.tag.is_within(&outer.tag)
+            && self.comment.is_within(&outer.comment)
+    }
+}
+
+impl Default for ValidationPolicy {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stricter_policy_is_within_default() {
+        let mut policy = ValidationPolicy::DEFAULT;
+        assert!(policy.is_within(&ValidationPolicy::DEFAULT));
+
+        policy.title.max_length = 20;
+        policy.description.allowed = AllowedCharacters::SingleLine;
+        assert!(policy.is_within(&ValidationPolicy::DEFAULT));
+
+        policy.assignee.max_length = 100;
+        assert!(!policy.is_within(&ValidationPolicy::DEFAULT));
+
+        let mut policy = ValidationPolicy::DEFAULT;
+        policy.title.allowed = AllowedCharacters::MultiLine;
+        assert!(!policy.is_within(&ValidationPolicy::DEFAULT));
+    }
+
+    #[test]
+    fn test_normalization_is_part_of_strictness() {
+        let mut policy = ValidationPolicy::DEFAULT;
+        policy.tag.normalization = Normalization::Collapse;
+        assert!(policy.is_within(&ValidationPolicy::DEFAULT));
+
+        policy.tag.normalization = Normalization::None;
+        assert!(!policy.is_within(&ValidationPolicy::DEFAULT));
+    }
+}
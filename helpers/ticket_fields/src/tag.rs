@@ -0,0 +1,58 @@
+use crate::policy::ValidationPolicy;
+use crate::text::{self, text_field};
+
+text_field! {
+    /// A label used to categorise tickets.
+    ///
+    /// Tags are case-insensitive: they are stored in lowercase,
+    /// and cannot contain whitespace.
+    pub struct TicketTag;
+    pub enum TicketTagError for "tag" {
+        Whitespace => "The tag cannot contain whitespace",
+    }
+}
+
+impl TicketTag {
+    pub fn new(tag: &str, policy: &ValidationPolicy) -> Result<Self, TicketTagError> {
+        let tag = tag.to_lowercase();
+        let tag = text::apply(&tag, &policy.tag)?;
+        if tag.chars().any(char::is_whitespace) {
+            return Err(TicketTagError::Whitespace);
+        }
+        Ok(Self(tag.into_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::overly_long_title;
+
+    #[test]
+    fn test_try_from_long_string() {
+        let err = TicketTag::try_from(overly_long_title()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The tag cannot be longer than 30 characters, but it has 84"
+        );
+    }
+
+    #[test]
+    fn test_tags_are_lowercase() {
+        let tag = TicketTag::try_from("  BackEnd ").unwrap();
+        assert_eq!(tag, "backend");
+    }
+
+    #[test]
+    fn test_length_is_measured_in_lowercase() {
+        // "İ" lowercases to "i" followed by a combining dot.
+        let tag = TicketTag::try_from("İ".repeat(30)).unwrap();
+        assert_eq!(tag.as_str(), "i\u{307}".repeat(30));
+    }
+
+    #[test]
+    fn test_try_from_inner_whitespace() {
+        let err = TicketTag::try_from("good first issue").unwrap_err();
+        assert_eq!(err.to_string(), "The tag cannot contain whitespace");
+    }
+}
@@ -1,5 +1,5 @@
-use crate::{TicketDescription, TicketTitle};
-use common::{valid_description, valid_title};
+use crate::{CommentBody, TicketAssignee, TicketDescription, TicketTag, TicketTitle};
+use common::{valid_assignee, valid_comment, valid_description, valid_tag, valid_title};
 
 /// A function to generate a valid ticket title,
 /// for test purposes.
@@ -12,3 +12,21 @@ pub fn ticket_title() -> TicketTitle {
 pub fn ticket_description() -> TicketDescription {
     valid_description().try_into().unwrap()
 }
+
+/// A function to generate a valid ticket assignee,
+/// for test purposes.
+pub fn ticket_assignee() -> TicketAssignee {
+    valid_assignee().try_into().unwrap()
+}
+
+/// A function to generate a valid ticket tag,
+/// for test purposes.
+pub fn ticket_tag() -> TicketTag {
+    valid_tag().try_into().unwrap()
+}
+
+/// A function to generate a valid comment body,
+/// for test purposes.
+pub fn comment_body() -> CommentBody {
+    valid_comment().try_into().unwrap()
+}
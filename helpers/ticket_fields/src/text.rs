@@ -0,0 +1,307 @@
+use std::borrow::Cow;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::policy::{AllowedCharacters, FieldPolicy, Normalization};
+
+/// Why a value was rejected by a [`FieldPolicy`].
+/// Each field type turns it into its own error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Violation {
+    Empty,
+    TooShort { min: usize, actual: usize },
+    TooLong { max: usize, actual: usize },
+    ControlCharacters,
+}
+
+/// Declares a validated text field: the newtype around a `String`, its error type
+/// and the conversions every field shares. Each field writes its own `new`
+/// constructor, which validates against its part of a `ValidationPolicy`.
+///
+/// The error gets one variant per [`Violation`], plus the `extra` ones that only
+/// this field's constructor returns.
+macro_rules! text_field {
+    (
+        $(#[$meta:meta])*
+        pub struct $name:ident;
+        pub enum $error:ident for $label:literal $({
+            $($extra:ident => $message:literal,)*
+        })?
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, PartialEq, Clone, Eq, PartialOrd, Ord, Hash)]
+        #[cfg_attr(
+            feature = "serde",
+            derive(serde::Serialize, serde::Deserialize),
+            serde(into = "String", try_from = "String")
+        )]
+        pub struct $name(String);
+
+        #[derive(Debug, PartialEq, Eq, thiserror::Error)]
+        pub enum $error {
+            #[error("The {} cannot be empty", $label)]
+            Empty,
+            #[error("The {} cannot be shorter than {min} characters, but it has {actual}", $label)]
+            TooShort { min: usize, actual: usize },
+            #[error("The {} cannot be longer than {max} characters, but it has {actual}", $label)]
+            TooLong { max: usize, actual: usize },
+            #[error("The {} cannot contain control characters", $label)]
+            ControlCharacters,
+            $($(
+                #[error($message)]
+                $extra,
+            )*)?
+        }
+
+        impl From<$crate::text::Violation> for $error {
+            fn from(violation: $crate::text::Violation) -> Self {
+                use $crate::text::Violation;
+                match violation {
+                    Violation::Empty => Self::Empty,
+                    Violation::TooShort { min, actual } => Self::TooShort { min, actual },
+                    Violation::TooLong { max, actual } => Self::TooLong { max, actual },
+                    Violation::ControlCharacters => Self::ControlCharacters,
+                }
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl $error {
+            /// Recovers the error from a message that starts with its `Display`
+            /// output, like the message of a failed deserialization.
+            pub(crate) fn from_message(message: &str) -> Option<Self> {
+                $($(
+                    if message.starts_with($message) {
+                        return Some(Self::$extra);
+                    }
+                )*)?
+                $crate::text::parse_violation(message, $label).map(Self::from)
+            }
+        }
+
+        impl $name {
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl TryFrom<String> for $name {
+            type Error = $error;
+
+            fn try_from(value: String) -> Result<Self, Self::Error> {
+                Self::new(&value, &$crate::ValidationPolicy::DEFAULT)
+            }
+        }
+
+        impl TryFrom<&str> for $name {
+            type Error = $error;
+
+            fn try_from(value: &str) -> Result<Self, Self::Error> {
+                Self::new(value, &$crate::ValidationPolicy::DEFAULT)
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.0 == *other
+            }
+        }
+    };
+}
+
+pub(crate) use text_field;
+
+/// Normalizes `value` and checks it against `policy`,
+/// returning the value that should be stored.
+pub(crate) fn apply<'a>(value: &'a str, policy: &FieldPolicy) -> Result<Cow<'a, str>, Violation> {
+    let value = normalize(value, policy.normalization);
+
+    let length = length(&value);
+    if value.is_empty() {
+        Err(Violation::Empty)
+    } else if length < policy.min_length {
+        Err(Violation::TooShort {
+            min: policy.min_length,
+            actual: length,
+        })
+    } else if length > policy.max_length {
+        Err(Violation::TooLong {
+            max: policy.max_length,
+            actual: length,
+        })
+    } else if has_control_characters(&value, policy.allowed) {
+        Err(Violation::ControlCharacters)
+    } else {
+        Ok(value)
+    }
+}
+
+/// Parses the `Display` output of a [`Violation`] of the field called `label`,
+/// ignoring whatever follows it.
+#[cfg(feature = "serde")]
+pub(crate) fn parse_violation(message: &str, label: &str) -> Option<Violation> {
+    let message = message
+        .strip_prefix("The ")?
+        .strip_prefix(label)?
+        .strip_prefix(" cannot ")?;
+
+    if message.starts_with("be empty") {
+        Some(Violation::Empty)
+    } else if message.starts_with("contain control characters") {
+        Some(Violation::ControlCharacters)
+    } else if let Some(counts) = message.strip_prefix("be shorter than ") {
+        let (min, actual) = parse_counts(counts)?;
+        Some(Violation::TooShort { min, actual })
+    } else if let Some(counts) = message.strip_prefix("be longer than ") {
+        let (max, actual) = parse_counts(counts)?;
+        Some(Violation::TooLong { max, actual })
+    } else {
+        None
+    }
+}
+
+// Parses "<limit> characters, but it has <actual>".
+#[cfg(feature = "serde")]
+fn parse_counts(message: &str) -> Option<(usize, usize)> {
+    let (limit, message) = parse_number(message)?;
+    let message = message.strip_prefix(" characters, but it has ")?;
+    let (actual, _) = parse_number(message)?;
+    Some((limit, actual))
+}
+
+#[cfg(feature = "serde")]
+fn parse_number(message: &str) -> Option<(usize, &str)> {
+    let end = message
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(message.len());
+    let (number, rest) = message.split_at(end);
+    Some((number.parse().ok()?, rest))
+}
+
+fn normalize(value: &str, normalization: Normalization) -> Cow<'_, str> {
+    match normalization {
+        Normalization::None => Cow::Borrowed(value),
+        Normalization::Trim => Cow::Borrowed(value.trim()),
+        Normalization::Collapse => {
+            let mut words = value.split_whitespace();
+            let first = words.next().unwrap_or_default();
+            words.fold(Cow::Borrowed(first), |mut collapsed, word| {
+                let collapsed_mut = collapsed.to_mut();
+                collapsed_mut.push(' ');
+                collapsed_mut.push_str(word);
+                collapsed
+            })
+        }
+    }
+}
+
+/// The length of `value` as a reader would count it: one per grapheme cluster,
+/// regardless of how many bytes or `char`s it takes to encode.
+fn length(value: &str) -> usize {
+    value.graphemes(true).count()
+}
+
+fn has_control_characters(value: &str, allowed: AllowedCharacters) -> bool {
+    match allowed {
+        AllowedCharacters::SingleLine => value.chars().any(char::is_control),
+        AllowedCharacters::MultiLine => value
+            .chars()
+            .any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t')),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_length_counts_graphemes() {
+        assert_eq!(length("A title"), 7);
+        assert_eq!(length("日本語のタイトル"), 8);
+        assert_eq!(length("e\u{301}"), 1);
+        assert_eq!(length("👩‍👩‍👧"), 1);
+    }
+
+    #[test]
+    fn test_control_characters() {
+        assert!(has_control_characters(
+            "A\ttitle",
+            AllowedCharacters::SingleLine
+        ));
+        assert!(!has_control_characters(
+            "A\ndescription",
+            AllowedCharacters::MultiLine
+        ));
+        assert!(has_control_characters(
+            "A\u{7}description",
+            AllowedCharacters::MultiLine
+        ));
+    }
+
+    #[test]
+    fn test_apply() {
+        let policy = FieldPolicy {
+            min_length: 2,
+            max_length: 5,
+            allowed: AllowedCharacters::SingleLine,
+            normalization: Normalization::Trim,
+        };
+        assert_eq!(apply(" abc \n", &policy), Ok(Cow::Borrowed("abc")));
+        assert_eq!(apply("", &policy), Err(Violation::Empty));
+        assert_eq!(apply(" \t \n", &policy), Err(Violation::Empty));
+        assert_eq!(
+            apply("a", &policy),
+            Err(Violation::TooShort { min: 2, actual: 1 })
+        );
+        assert_eq!(
+            apply("abcdef", &policy),
+            Err(Violation::TooLong { max: 5, actual: 6 })
+        );
+        assert_eq!(
+            apply("日本語のタ", &policy),
+            Ok(Cow::Borrowed("日本語のタ"))
+        );
+        assert_eq!(apply("a\tb", &policy), Err(Violation::ControlCharacters));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_parse_violation() {
+        for violation in [
+            Violation::Empty,
+            Violation::TooShort { min: 2, actual: 1 },
+            Violation::TooLong {
+                max: 50,
+                actual: 51,
+            },
+            Violation::ControlCharacters,
+        ] {
+            let message = crate::TicketTitleError::from(violation).to_string();
+            assert_eq!(parse_violation(&message, "title"), Some(violation));
+            assert_eq!(
+                parse_violation(&format!("{message} at line 1 column 20"), "title"),
+                Some(violation)
+            );
+            assert_eq!(parse_violation(&message, "description"), None);
+        }
+        assert_eq!(
+            parse_violation("The title cannot be longer than", "title"),
+            None
+        );
+        assert_eq!(parse_violation("missing field `title`", "title"), None);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(" a  b ", Normalization::None), " a  b ");
+        assert_eq!(normalize(" a  b ", Normalization::Trim), "a  b");
+        assert_eq!(normalize(" a \t b\n", Normalization::Collapse), "a b");
+        assert_eq!(normalize("   ", Normalization::Collapse), "");
+    }
+}
@@ -1,41 +1,16 @@
-use std::convert::TryFrom;
+use crate::policy::ValidationPolicy;
+use crate::text::{self, text_field};
 
-#[derive(Debug, PartialEq, Clone, Eq)]
-pub struct TicketTitle(String);
-
-#[derive(Debug, thiserror::Error)]
-pub enum TicketTitleError {
-    #[error("The title cannot be empty")]
-    Empty,
-    #[error("The title cannot be longer than 50 characters")]
-    TooLong,
-}
-
-impl TryFrom<String> for TicketTitle {
-    type Error = TicketTitleError;
-
-    fn try_from(value: String) -> Result<Self, Self::Error> {
-        validate(&value)?;
-        Ok(Self(value))
-    }
+text_field! {
+    /// The one-line summary of a ticket.
+    pub struct TicketTitle;
+    pub enum TicketTitleError for "title"
 }
 
-impl TryFrom<&str> for TicketTitle {
-    type Error = TicketTitleError;
-
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        validate(value)?;
-        Ok(Self(value.to_string()))
-    }
-}
-
-fn validate(title: &str) -> Result<(), TicketTitleError> {
-    if title.is_empty() {
-        Err(TicketTitleError::Empty)
-    } else if title.len() > 50 {
-        Err(TicketTitleError::TooLong)
-    } else {
-        Ok(())
+impl TicketTitle {
+    pub fn new(title: &str, policy: &ValidationPolicy) -> Result<Self, TicketTitleError> {
+        let title = text::apply(title, &policy.title)?;
+        Ok(Self(title.into_owned()))
     }
 }
 
@@ -43,7 +18,6 @@ fn validate(title: &str) -> Result<(), TicketTitleError> {
 mod tests {
     use super::*;
     use common::{overly_long_title, valid_title};
-    use std::convert::TryFrom;
 
     #[test]
     fn test_try_from_string() {
@@ -63,7 +37,30 @@ mod tests {
         let err = TicketTitle::try_from(overly_long_title()).unwrap_err();
         assert_eq!(
             err.to_string(),
-            "The title cannot be longer than 50 characters"
+            "The title cannot be longer than 50 characters, but it has 84"
+        );
+    }
+
+    #[test]
+    fn test_length_is_counted_in_characters() {
+        let input = "日本語で書かれたチケットのタイトルです。二十文字";
+        assert!(input.len() > 50);
+        let title = TicketTitle::try_from(input).unwrap();
+        assert_eq!(title.0, input);
+    }
+
+    #[test]
+    fn test_length_is_counted_in_graphemes() {
+        // Each "é" is an "e" followed by a combining accent: two chars, one grapheme.
+        let input = "e\u{301}".repeat(50);
+        assert_eq!(input.chars().count(), 100);
+        let title = TicketTitle::try_from(input.as_str()).unwrap();
+        assert_eq!(title.0, input);
+
+        let err = TicketTitle::try_from("e\u{301}".repeat(51)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The title cannot be longer than 50 characters, but it has 51"
         );
     }
 
@@ -72,4 +69,47 @@ mod tests {
         let title = TicketTitle::try_from("A title").unwrap();
         assert_eq!(title.0, "A title");
     }
+
+    #[test]
+    fn test_try_from_control_characters() {
+        let err = TicketTitle::try_from("A\ttitle").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The title cannot contain control characters"
+        );
+    }
+
+    #[test]
+    fn test_new_with_custom_policy() {
+        let mut policy = ValidationPolicy::default();
+        policy.title.min_length = 3;
+        policy.title.max_length = 15;
+        policy.title.normalization = crate::Normalization::Collapse;
+
+        let title = TicketTitle::new("  A   short\n title ", &policy).unwrap();
+        assert_eq!(title.as_str(), "A short title");
+
+        let err = TicketTitle::new("Ab", &policy).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The title cannot be shorter than 3 characters, but it has 2"
+        );
+
+        let err = TicketTitle::new("A much longer title", &policy).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The title cannot be longer than 15 characters, but it has 19"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserialize_validates() {
+        let title: TicketTitle = serde_json::from_str(r#"" A title ""#).unwrap();
+        assert_eq!(title, "A title");
+        assert_eq!(serde_json::to_string(&title).unwrap(), r#""A title""#);
+
+        let err = serde_json::from_str::<TicketTitle>(r#""  ""#).unwrap_err();
+        assert!(err.to_string().starts_with("The title cannot be empty"));
+    }
 }